@@ -0,0 +1,171 @@
+//! `bf [options] <program.b>`
+//!
+//! Run a brainfuck program, reading stdin and writing stdout, with
+//! the same semantics as `brainfuck!` unless the options say
+//! otherwise.
+
+#![feature(env, io, path)]
+
+extern crate bf_interpreter;
+
+use std::default::Default;
+use std::env;
+use std::old_io as io;
+
+use bf_interpreter::{Bounds, Config, Dialect, Eof, Profile, Program, Stats};
+
+const USAGE: &'static str = "\
+usage: bf [options] <program.b>
+
+Options:
+    --tape-len <cells>     the number of cells on the tape (default 30000)
+    --cell-width <bits>    the width of each cell: 8 (default), 16 or 32
+    --eof <mode>           what `,` does at the end of the input:
+                           minus-one (default), zero or unchanged
+    --bounds <policy>      what happens when the pointer moves off the
+                           tape: clamp (default), wrap or error
+    --dialect <name>       the dialect of the program: brainfuck
+                           (default), pbrain, brainfork, ebf1, boolfuck
+                           or smallfuck
+    --fuel <steps>         stop after running this many instructions
+    --stats                print what the program did to stderr
+    --profile              print how often each command ran, and the
+                           hottest loops, to stderr
+    -h, --help             print this message";
+
+struct Args {
+    config: Config,
+    dialect: Dialect,
+    stats: bool,
+    profile: bool,
+    path: String,
+}
+
+/// The number of loops in the `--profile` table.
+const HOTTEST: usize = 10;
+
+fn main() {
+    let args = match parse_args() {
+        Ok(Some(args)) => args,
+        Ok(None) => return println!("{}", USAGE),
+        Err(msg) => return fail(2, &*msg)
+    };
+
+    let src = match io::File::open(&Path::new(&*args.path)).read_to_end() {
+        Ok(src) => src,
+        Err(e) => return fail(2, &*format!("bf: couldn't read `{}`: {}", args.path, e))
+    };
+    let program = match Program::parse_dialect(&*src, args.dialect) {
+        Ok(program) => program,
+        Err(e) => return fail(2, &*format!("bf: {}: {}", args.path, e))
+    };
+
+    let mut stats = Stats::default();
+    let mut stdout = io::stdout();
+    let mut profile = Profile::default();
+    let result = if args.profile {
+        program.run_profiled(&args.config, &mut stats, &mut profile, &mut io::stdin(), &mut stdout)
+    } else {
+        program.run_with(&args.config, &mut stats, &mut io::stdin(), &mut stdout)
+    };
+    let _ = stdout.flush();
+
+    if args.stats {
+        let _ = writeln!(&mut io::stderr(),
+                         "steps: {}\ncells: {}\ninput: {} bytes\noutput: {} bytes",
+                         stats.steps, stats.cells, stats.input, stats.output);
+    }
+    if args.profile {
+        let _ = write!(&mut io::stderr(), "profile of {}: {}", args.path, profile.table(HOTTEST));
+    }
+    if let Err(e) = result {
+        fail(1, &*format!("bf: {}", e))
+    }
+}
+
+/// Parse the arguments, returning `None` when asked for help.
+fn parse_args() -> Result<Option<Args>, String> {
+    let mut config: Config = Default::default();
+    let mut dialect = Dialect::Brainfuck;
+    let mut stats = false;
+    let mut profile = false;
+    let mut path = None;
+
+    let mut args = env::args().skip(1);
+    while let Some(arg) = args.next() {
+        let flag = &*arg;
+        match flag {
+            "-h" | "--help" => return Ok(None),
+            "--stats" => {
+                stats = true;
+                continue
+            }
+            "--profile" => {
+                profile = true;
+                continue
+            }
+            _ if !flag.starts_with("-") => {
+                if path.is_some() {
+                    return Err(USAGE.to_string())
+                }
+                path = Some(arg.clone());
+                continue
+            }
+            _ => {}
+        }
+
+        let value = match args.next() {
+            Some(value) => value,
+            None => return Err(format!("bf: `{}` needs a value", flag))
+        };
+        let value = &*value;
+        let invalid = || Err(format!("bf: invalid value `{}` for `{}`", value, flag));
+        match flag {
+            "--tape-len" => match value.parse() {
+                Ok(n) if n > 0 => config.tape_len = n,
+                _ => return invalid()
+            },
+            "--cell-width" => match value.parse() {
+                Ok(bits @ 8) | Ok(bits @ 16) | Ok(bits @ 32) => config.cell_bits = bits,
+                _ => return invalid()
+            },
+            "--eof" => config.eof = match value {
+                "minus-one" => Eof::MinusOne,
+                "zero" => Eof::Zero,
+                "unchanged" => Eof::Unchanged,
+                _ => return invalid()
+            },
+            "--bounds" => config.bounds = match value {
+                "clamp" => Bounds::Clamp,
+                "wrap" => Bounds::Wrap,
+                "error" => Bounds::Error,
+                _ => return invalid()
+            },
+            "--dialect" => match Dialect::from_name(value) {
+                Some(d) => dialect = d,
+                None => return invalid()
+            },
+            "--fuel" => match value.parse() {
+                Ok(n) => config.fuel = Some(n),
+                _ => return invalid()
+            },
+            _ => return Err(format!("bf: unknown option `{}`\n\n{}", flag, USAGE))
+        }
+    }
+
+    match path {
+        Some(path) => Ok(Some(Args {
+            config: config,
+            dialect: dialect,
+            stats: stats,
+            profile: profile,
+            path: path,
+        })),
+        None => Err(USAGE.to_string())
+    }
+}
+
+fn fail(status: i32, msg: &str) {
+    let _ = writeln!(&mut io::stderr(), "{}", msg);
+    env::set_exit_status(status)
+}
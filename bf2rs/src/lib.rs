@@ -0,0 +1,81 @@
+//! Translating brainfuck programs into Rust source, using exactly the
+//! same code generation as `brainfuck!`, so that the result can be
+//! read, vendored and compiled without the compiler plugin.
+
+#![crate_name="bf2rs"]
+#![crate_type="lib"]
+#![feature(rustc_private)]
+
+extern crate syntax;
+extern crate brainfuck_macros;
+
+use syntax::ast;
+use syntax::codemap;
+use syntax::ext::base::ExtCtxt;
+use syntax::ext::expand::ExpansionConfig;
+use syntax::parse::{self, token};
+use syntax::print::pprust;
+
+/// Generate a module containing a `run` function that's the same as
+/// the one `brainfuck!(options; program)` would give, where the
+/// `program` in the file `name` is read like a string literal given
+/// to the macro (or as tokens, when there's an `alphabet` option).
+///
+/// Errors and warnings are printed to stderr, just like the macro's.
+pub fn generate(name: &str, program: &str, options: &str) -> Result<String, ()> {
+    let sess = parse::new_parse_sess();
+    let mut cx = ExtCtxt::new(&sess, vec![], ExpansionConfig::default("bf2rs".to_string()));
+
+    let mut tts = parse::parse_tts_from_source_str("<options>".to_string(),
+                                                   options.to_string(),
+                                                   vec![], &sess);
+    let src = if uses_alphabet(&*tts) {
+        program.to_string()
+    } else {
+        raw_str(program)
+    };
+    if !tts.is_empty() {
+        tts.push(ast::TtToken(codemap::DUMMY_SP, token::Semi))
+    }
+    tts.extend(parse::parse_tts_from_source_str(name.to_string(), src,
+                                                vec![], &sess).into_iter());
+
+    let expr = brainfuck_macros::expand_brainfuck(&mut cx, codemap::DUMMY_SP, &*tts);
+    if sess.span_diagnostic.handler.has_errors() {
+        return Err(())
+    }
+
+    let options = if options.trim().is_empty() {
+        String::new()
+    } else {
+        format!(", with `{}`", options.trim())
+    };
+    Ok(format!("\
+// Generated by bf2rs from `{}`{}.
+
+pub fn run(r: &mut Reader, w: &mut Writer) -> ::std::old_io::IoResult<Vec<u8>> {{
+    let run: fn(&mut Reader, &mut Writer) -> ::std::old_io::IoResult<Vec<u8>> = {};
+    run(r, w)
+}}
+", name, options, pprust::expr_to_string(&*expr)))
+}
+
+/// Whether the options include `alphabet`, whose words have to be
+/// written as tokens rather than in a string literal.
+fn uses_alphabet(options: &[ast::TokenTree]) -> bool {
+    options.iter().any(|tt| match *tt {
+        ast::TtToken(_, token::Ident(id, _)) => &*token::get_ident(id) == "alphabet",
+        _ => false
+    })
+}
+
+/// `s` as a raw string literal, with enough `#`s that it can't end
+/// early. The `r#"` only shifts the first line, so the positions in
+/// errors still line up with the file.
+fn raw_str(s: &str) -> String {
+    let mut hashes = String::new();
+    while s.contains(&*format!("\"{}", hashes)) {
+        hashes.push('#')
+    }
+    format!("r{0}\"{1}\"{0}", hashes, s)
+}
@@ -0,0 +1,58 @@
+//! `bf2rs [--options '<options>'] <program.b> [<output.rs>]`
+//!
+//! Write the Rust that `brainfuck!(<options>; <program>)` expands to
+//! as a module with a `run` function, to `<output.rs>` or stdout.
+
+#![feature(env, io, path)]
+
+extern crate bf2rs;
+
+use std::env;
+use std::old_io as io;
+
+const USAGE: &'static str = "usage: bf2rs [--options '<options>'] <program.b> [<output.rs>]";
+
+fn main() {
+    let mut options = String::new();
+    let mut paths = vec![];
+    let mut args = env::args().skip(1);
+    while let Some(arg) = args.next() {
+        match &*arg {
+            "-o" | "--options" => match args.next() {
+                Some(o) => options = o,
+                None => return fail(USAGE)
+            },
+            "-h" | "--help" => {
+                println!("{}", USAGE);
+                return
+            }
+            _ => paths.push(arg)
+        }
+    }
+    if paths.len() != 1 && paths.len() != 2 {
+        return fail(USAGE)
+    }
+
+    let program = match io::File::open(&Path::new(&*paths[0])).read_to_string() {
+        Ok(p) => p,
+        Err(e) => return fail(&*format!("bf2rs: couldn't read `{}`: {}", paths[0], e))
+    };
+    let module = match bf2rs::generate(&*paths[0], &*program, &*options) {
+        Ok(m) => m,
+        // the errors have already been printed
+        Err(()) => return env::set_exit_status(1)
+    };
+
+    let result = match paths.get(1) {
+        Some(out) => io::File::create(&Path::new(&**out)).write_str(&*module),
+        None => io::stdout().write_str(&*module)
+    };
+    if let Err(e) = result {
+        fail(&*format!("bf2rs: couldn't write the output: {}", e))
+    }
+}
+
+fn fail(msg: &str) {
+    let _ = writeln!(&mut io::stderr(), "{}", msg);
+    env::set_exit_status(1)
+}
@@ -0,0 +1,35 @@
+extern crate bf2rs;
+
+static HELLO_WORLD: &'static str = "\
+    ++++++++[>++++[>++>+++>+++>+<<<<-]>+>+>->>+[<]<-]>>.>\n\
+    ---.+++++++..+++.>>.<-.<.+++.------.--------.>>+.>++.\n";
+
+#[test]
+fn module() {
+    let module = bf2rs::generate("hello.b", HELLO_WORLD, "").unwrap();
+    assert!(module.starts_with("// Generated by bf2rs from `hello.b`.\n"));
+    assert!(module.contains("pub fn run(r: &mut Reader, w: &mut Writer)"));
+
+    let module = bf2rs::generate("hello.b", HELLO_WORLD, "opt = 3").unwrap();
+    assert!(module.starts_with("// Generated by bf2rs from `hello.b`, with `opt = 3`.\n"));
+}
+
+#[test]
+fn plain_text() {
+    // comments that aren't valid Rust tokens, a `"#` that the raw
+    // string has to get around, and embedded input
+    assert!(bf2rs::generate("a.b", "it's \"# a comment ,[.,]!input\\", "").is_ok());
+}
+
+#[test]
+fn alphabet() {
+    let options = "alphabet { inc = ook, out = eek }";
+    assert!(bf2rs::generate("ook.b", "ook ook eek", options).is_ok());
+}
+
+#[test]
+fn errors() {
+    assert!(bf2rs::generate("a.b", "[[]", "").is_err());
+    assert!(bf2rs::generate("a.b", "+", "opt = 7").is_err());
+    assert!(bf2rs::generate("a.b", "+", "dialect = nope").is_err());
+}
@@ -0,0 +1,83 @@
+//! Compiles the module for each program with `rustc`, without the
+//! plugin, and checks that its `run` gives the same output and tape as
+//! `brainfuck!`.
+
+#![feature(plugin, io, path)]
+
+#[plugin] extern crate brainfuck_macros;
+extern crate bf2rs;
+
+use std::old_io::{BufReader, MemWriter, File, TempDir};
+use std::old_io::process::Command;
+
+/// Runs the generated module on stdin, writing the output to stdout
+/// and the final tape to stderr.
+static MAIN: &'static str = "\
+#![feature(io)]
+
+mod program;
+
+fn main() {
+    let mut stdout = std::old_io::stdout();
+    let tape = program::run(&mut std::old_io::stdin(), &mut stdout).unwrap();
+    stdout.flush().unwrap();
+    std::old_io::stderr().write(&*tape).unwrap();
+}
+";
+
+/// Generate the module for `src` with `options`, compile it and run it
+/// on `input`, returning the output and the tape.
+fn run_rustc(src: &str, options: &str, input: &str) -> (Vec<u8>, Vec<u8>) {
+    let dir = TempDir::new("bf2rs").unwrap();
+    let main = dir.path().join("main.rs");
+    let exe = dir.path().join("program");
+    File::create(&dir.path().join("program.rs"))
+        .write_str(&*bf2rs::generate("program.b", src, options).unwrap()).unwrap();
+    File::create(&main).write_str(MAIN).unwrap();
+
+    let rustc = Command::new("rustc").arg("-o").arg(&exe).arg(&main).output().unwrap();
+    assert!(rustc.status.success(), "rustc failed: {}", String::from_utf8_lossy(&*rustc.error));
+
+    let mut process = Command::new(&exe).spawn().unwrap();
+    process.stdin.take().unwrap().write_str(input).unwrap();
+    let result = process.wait_with_output().unwrap();
+    assert!(result.status.success());
+    (result.output, result.error)
+}
+
+/// Check that `run` and the macro agree on the `input`, and that the
+/// output is `expected`.
+fn agree(run: fn(&mut Reader, &mut Writer) -> std::old_io::IoResult<Vec<u8>>,
+         src: &str, options: &str, input: &str, expected: &[u8]) {
+    let mut macro_out = MemWriter::new();
+    let macro_tape = run(&mut BufReader::new(input.as_bytes()), &mut macro_out).unwrap();
+
+    let (out, tape) = run_rustc(src, options, input);
+    assert_eq!(out, macro_out.into_inner());
+    assert!(tape == macro_tape);
+    assert_eq!(&*out, expected);
+}
+
+#[test]
+fn hello_world() {
+    agree(brainfuck!("++++++++[>++++[>++>+++>+++>+<<<<-]>+>+>->>+[<]<-]>>.>\
+                      ---.+++++++..+++.>>.<-.<.+++.------.--------.>>+.>++."),
+          "++++++++[>++++[>++>+++>+++>+<<<<-]>+>+>->>+[<]<-]>>.>\
+           ---.+++++++..+++.>>.<-.<.+++.------.--------.>>+.>++.",
+          "", "", b"Hello World!\n");
+}
+
+#[test]
+fn input() {
+    agree(brainfuck!(",+[-.,+]"), ",+[-.,+]", "", "Hello", b"Hello");
+    agree(brainfuck!(",+[-.,+]!embedded, "), ",+[-.,+]!embedded, ", "", "input",
+          b"embedded, input");
+}
+
+#[test]
+fn options() {
+    agree(brainfuck!(opt = 3; "+++[->++>+++<<]>.>.<<+[>+]<[->>+<<]<."),
+          "+++[->++>+++<<]>.>.<<+[>+]<[->>+<<]<.", "opt = 3", "", &[6, 9, 1]);
+    agree(brainfuck!(dialect = pbrain; "+(+++++.):[-]+:"), "+(+++++.):[-]+:",
+          "dialect = pbrain", "", &[6, 6]);
+}
@@ -6,7 +6,10 @@
 
 #[plugin] extern crate brainfuck_macros;
 
-
+/// The source of the interpreter, for running it in other ways (with
+/// the URL at the top, which `brainfuck!` sees as a comment,
+/// removed).
+pub static SOURCE: &'static [u8] = include_bytes!("cgbfi2.b");
 
 /// Return a function that was created by the `brainfuck!` macro
 /// running on a brainfuck interpreter written in brainfuck. (Yo
@@ -0,0 +1,223 @@
+//! Translating brainfuck programs into self-contained C, with the
+//! same semantics as `brainfuck!`: 30,000 wrapping `unsigned char`
+//! cells, `,` reading -1 (255) at the end of the input, and the
+//! pointer clamped at the ends of the tape. The program reads stdin
+//! and writes stdout, and exits with status 1 if either fails.
+
+#![crate_name="bf_c"]
+#![crate_type="lib"]
+
+extern crate bf_ir;
+
+use bf_ir::{opt, Op};
+
+pub use bf_ir::Error;
+
+/// The number of cells on the tape.
+pub const TAPE_LEN: usize = 30_000;
+
+/// Parse, optimise and translate `src`, in the same format as
+/// `bf_interpreter::Program::parse`.
+pub fn translate(src: &[u8]) -> Result<String, Error> {
+    let mut program = try!(bf_ir::parse(src));
+    program.ops = opt::optimise(program.ops, &*opt::passes(opt::MAX_LEVEL), false);
+    translate_ir(&program)
+}
+
+/// Translate a program, which can only contain the plain brainfuck
+/// ops and those from `bf_ir::opt` (any `Debug`s are ignored), or
+/// else fails with `Error::Unsupported`.
+pub fn translate_ir(program: &bf_ir::Program) -> Result<String, Error> {
+    let mut c = C { out: String::new(), depth: 1, used: [false; 4] };
+    try!(c.ops(&*program.ops));
+
+    let mut out = format!("\
+#include <stdio.h>
+#include <stdlib.h>
+
+#define TAPE_LEN {}
+
+static unsigned char tape[TAPE_LEN];
+static size_t i;
+", TAPE_LEN);
+    // only the helpers that are used, to avoid warnings
+    if c.used[LEFT] {
+        out.push_str("
+static void left(size_t n) {
+    i = i > n ? i - n : 0;
+}
+")
+    }
+    if c.used[RIGHT] {
+        out.push_str("
+static void right(size_t n) {
+    i = i + n < TAPE_LEN ? i + n : TAPE_LEN - 1;
+}
+")
+    }
+    if c.used[IN] {
+        if !program.input.is_empty() {
+            out.push_str(&*format!("
+/* the input embedded after the first `!`, read before stdin */
+static const char input[] = \"{}\";
+static size_t input_pos;
+", escape(&*program.input)))
+        }
+        out.push_str("
+static void in(void) {
+    int c;
+");
+        if !program.input.is_empty() {
+            out.push_str("    if (input_pos < sizeof input - 1) {
+        tape[i] = input[input_pos++];
+        return;
+    }
+")
+        }
+        out.push_str("    c = getchar();
+    if (c == EOF && ferror(stdin)) exit(1);
+    tape[i] = c == EOF ? 255 : c;
+}
+")
+    }
+    if c.used[OUT] {
+        out.push_str("
+static void out(void) {
+    if (putchar(tape[i]) == EOF) exit(1);
+}
+")
+    }
+    out.push_str(&*format!("
+int main(void) {{
+{}    if (fflush(stdout) == EOF) return 1;
+    return 0;
+}}
+", c.out));
+    Ok(out)
+}
+
+/// `s` as the inside of a C string literal, with everything but
+/// letters and digits as three-digit octal escapes (so that nothing
+/// can be misread as a trigraph or part of another escape).
+fn escape(s: &[u8]) -> String {
+    let mut out = String::new();
+    for &b in s.iter() {
+        if (b as char).is_alphanumeric() && b < 0x80 {
+            out.push(b as char)
+        } else {
+            out.push_str(&*format!("\\{:03o}", b))
+        }
+    }
+    out
+}
+
+// the helper functions, indexing `C::used`
+const LEFT: usize = 0;
+const RIGHT: usize = 1;
+const IN: usize = 2;
+const OUT: usize = 3;
+
+struct C {
+    out: String,
+    /// The current indentation, in levels of four spaces.
+    depth: usize,
+    /// Which helper functions are called.
+    used: [bool; 4],
+}
+
+impl C {
+    fn line(&mut self, line: &str) {
+        for _ in 0..self.depth {
+            self.out.push_str("    ")
+        }
+        self.out.push_str(line);
+        self.out.push('\n')
+    }
+
+    fn ops(&mut self, ops: &[Op]) -> Result<(), Error> {
+        for op in ops.iter() {
+            try!(self.op(op))
+        }
+        Ok(())
+    }
+
+    fn op(&mut self, op: &Op) -> Result<(), Error> {
+        match *op {
+            Op::Add(n) => self.line(&*format!("tape[i] += {};", n as u8)),
+            Op::Move(n) => {
+                let line = self.move_(n);
+                self.line(&*line)
+            }
+            Op::In => {
+                self.used[IN] = true;
+                self.line("in();")
+            }
+            Op::Out => {
+                self.used[OUT] = true;
+                self.line("out();")
+            }
+            Op::Clear => self.line("tape[i] = 0;"),
+            Op::Loop(_, ref body) => try!(self.loop_(&**body)),
+            Op::Scan(n) => {
+                let line = format!("while (tape[i]) {}", self.move_(n));
+                self.line(&*line)
+            }
+            // multiplication loops like `[->+>++<<]`, unless they
+            // would go off the ends of the tape, in which case the
+            // original loop is run so that the pointer is clamped
+            // the same way
+            Op::Mul(_, ref targets, ref body) => {
+                let (lo, hi) = opt::visited(&**body);
+                self.line("if (tape[i]) {");
+                self.depth += 1;
+                let in_bounds = if lo < 0 {
+                    format!("i >= {} && i + {} < TAPE_LEN", -lo, hi)
+                } else {
+                    format!("i + {} < TAPE_LEN", hi)
+                };
+                self.line(&*format!("if ({}) {{", in_bounds));
+                self.depth += 1;
+                self.line("unsigned char c = tape[i];");
+                for &(off, n) in targets.iter() {
+                    let target = if off < 0 {
+                        format!("i - {}", -off)
+                    } else {
+                        format!("i + {}", off)
+                    };
+                    self.line(&*format!("tape[{}] += c * {};", target, n as u8));
+                }
+                self.line("tape[i] = 0;");
+                self.depth -= 1;
+                self.line("} else {");
+                self.depth += 1;
+                try!(self.loop_(&**body));
+                self.depth -= 1;
+                self.line("}");
+                self.depth -= 1;
+                self.line("}");
+            }
+            Op::Debug(_) => {}
+            _ => return Err(Error::Unsupported)
+        }
+        Ok(())
+    }
+
+    fn loop_(&mut self, body: &[Op]) -> Result<(), Error> {
+        self.line("while (tape[i]) {");
+        self.depth += 1;
+        try!(self.ops(body));
+        self.depth -= 1;
+        self.line("}");
+        Ok(())
+    }
+
+    fn move_(&mut self, n: isize) -> String {
+        if n < 0 {
+            self.used[LEFT] = true;
+            format!("left({});", -n)
+        } else {
+            self.used[RIGHT] = true;
+            format!("right({});", n)
+        }
+    }
+}
@@ -0,0 +1,69 @@
+//! Compiles the C for each program with the system `cc`, and checks
+//! that it gives the same output as `brainfuck!`.
+
+#![feature(plugin, io, path)]
+
+#[plugin] extern crate brainfuck_macros;
+extern crate bf_c;
+extern crate bf_ir;
+
+use std::old_io::{BufReader, MemWriter, File, TempDir};
+use std::old_io::process::Command;
+
+/// Compile and run the C for `src` on `input`, returning the output.
+fn run_c(src: &str, input: &str) -> Vec<u8> {
+    let dir = TempDir::new("bf_c").unwrap();
+    let c_file = dir.path().join("program.c");
+    let exe = dir.path().join("program");
+    File::create(&c_file).write_str(&*bf_c::translate(src.as_bytes()).unwrap()).unwrap();
+
+    let cc = Command::new("cc").arg("-o").arg(&exe).arg(&c_file).output().unwrap();
+    assert!(cc.status.success(), "cc failed: {}", String::from_utf8_lossy(&*cc.error));
+
+    let mut process = Command::new(&exe).spawn().unwrap();
+    process.stdin.take().unwrap().write_str(input).unwrap();
+    let result = process.wait_with_output().unwrap();
+    assert!(result.status.success());
+    result.output
+}
+
+/// Define a test that runs the string literal `$prog` through both
+/// the macro and the C compiler, checking they agree and that the
+/// output is `$expected`.
+macro_rules! agree {
+    ($name: ident, $prog: tt, $input: expr, $expected: expr) => {
+        #[test]
+        fn $name() {
+            let mut input = BufReader::new($input.as_bytes());
+            let mut macro_out = MemWriter::new();
+            brainfuck!($prog)(&mut input, &mut macro_out).unwrap();
+
+            let out = run_c($prog, $input);
+            assert_eq!(out, macro_out.into_inner());
+            assert_eq!(&*out, &$expected[..]);
+        }
+    }
+}
+
+agree!(hello_world,
+       "++++++++[>++++[>++>+++>+++>+<<<<-]>+>+>->>+[<]<-]>>.>\
+        ---.+++++++..+++.>>.<-.<.+++.------.--------.>>+.>++.",
+       "", b"Hello World!\n");
+agree!(cat, ",+[-.,+]", "Hello", b"Hello");
+agree!(eof, ",.", "", [255]);
+agree!(wrapping, "-.+.", "", [255, 0]);
+agree!(left_clamp, "<<+.", "", [1]);
+// fills the tape with 1s, until the last cell wraps around to 0
+agree!(right_clamp, "+[>+]<.", "", [1]);
+agree!(embedded_input, ",+[-.,+]!Hello, \"?\\", "world", b"Hello, \"?\\world");
+agree!(mul, "+++[->++>+++<<]>.>.", "", [6, 9]);
+// the multiplication starts next to the end of the tape
+agree!(mul_clamp, "+[>+]<[->>+<<]<.", "", [1]);
+agree!(scan, ">+>+>+<<[>]+<[<]>.>.>.>.>.", "", [1, 1, 1, 1, 0]);
+
+#[test]
+fn errors() {
+    assert_eq!(bf_c::translate(b"+[[]").err(), Some(bf_c::Error::Unclosed(1)));
+    let program = bf_ir::parse_dialect(b"+(-):", bf_ir::Dialect::Pbrain).unwrap();
+    assert_eq!(bf_c::translate_ir(&program).err(), Some(bf_c::Error::Unsupported));
+}
@@ -0,0 +1,169 @@
+//! Formatting brainfuck source: minifying it down to just the eight
+//! commands, or pretty-printing it with each loop on its own lines,
+//! and loops that contain other loops broken over lines and indented
+//! by their depth, like
+//!
+//! ```text
+//! +
+//! [
+//!   ->>>
+//!   [<++++>>++<-]
+//!   ++>>+>+>
+//!   [
+//!     >
+//!     [->>]
+//!     <<-
+//!   ]
+//! ]
+//! ```
+//!
+//! Neither changes what a program does: comments are dropped, and
+//! the input embedded after the first `!` is kept exactly.
+
+#![crate_name="bf_fmt"]
+#![crate_type="lib"]
+
+extern crate bf_ir;
+
+use bf_ir::Op;
+
+pub use bf_ir::Error;
+
+/// The longest line `pretty` writes, unless a single loop is longer.
+pub const WIDTH: usize = 72;
+
+/// The spaces per level of nesting.
+const INDENT: usize = 2;
+
+/// Split `src` into the code and the embedded input (including the
+/// `!`, if there is one).
+fn split(src: &[u8]) -> (&[u8], &[u8]) {
+    match src.iter().position(|&b| b == b'!') {
+        Some(i) => (&src[..i], &src[i..]),
+        None => (src, &[])
+    }
+}
+
+fn is_command(b: u8) -> bool {
+    match b {
+        b'+' | b'-' | b'<' | b'>' | b',' | b'.' | b'[' | b']' => true,
+        _ => false
+    }
+}
+
+/// Remove everything but the eight commands (and the embedded
+/// input). Unlike `pretty`, this works even if the loops don't match
+/// up.
+pub fn minify(src: &[u8]) -> Vec<u8> {
+    let (code, input) = split(src);
+    let mut out: Vec<u8> = code.iter().cloned().filter(|&b| is_command(b)).collect();
+    out.extend(input.iter().cloned());
+    out
+}
+
+/// Pretty-print `src`, putting each loop on its own lines (a single
+/// one if it doesn't contain other loops and fits, or else with its
+/// body indented between the brackets), and wrapping everything else
+/// at `WIDTH`.
+pub fn pretty(src: &[u8]) -> Result<Vec<u8>, Error> {
+    let program = try!(bf_ir::parse(src));
+    let mut printer = Printer { out: vec![], line: String::new(), depth: 0 };
+    printer.block(&*program.ops);
+    printer.flush();
+
+    let mut out = printer.out;
+    out.extend(split(src).1.iter().cloned());
+    Ok(out)
+}
+
+/// The command for a plain brainfuck op.
+fn command(op: &Op) -> char {
+    match *op {
+        Op::Add(1) => '+',
+        Op::Add(_) => '-',
+        Op::Move(1) => '>',
+        Op::Move(_) => '<',
+        Op::In => ',',
+        Op::Out => '.',
+        ref op => panic!("bf_fmt: unexpected op {:?}", op)
+    }
+}
+
+/// `[body]` on a single line.
+fn flat_loop(body: &[Op], out: &mut String) {
+    out.push('[');
+    for op in body.iter() {
+        match *op {
+            Op::Loop(_, ref body) => flat_loop(&**body, out),
+            ref op => out.push(command(op))
+        }
+    }
+    out.push(']')
+}
+
+struct Printer {
+    out: Vec<u8>,
+    /// The line so far, without the indentation.
+    line: String,
+    depth: usize,
+}
+
+impl Printer {
+    fn indent(&self) -> usize {
+        self.depth * INDENT
+    }
+
+    /// Add `s` to the current line, starting a new one if it doesn't
+    /// fit.
+    fn push(&mut self, s: &str) {
+        if !self.line.is_empty() && self.indent() + self.line.len() + s.len() > WIDTH {
+            self.flush()
+        }
+        self.line.push_str(s)
+    }
+
+    /// Finish the current line, if there's anything on it.
+    fn flush(&mut self) {
+        if self.line.is_empty() { return }
+        for _ in 0..self.indent() {
+            self.out.push(b' ')
+        }
+        self.out.extend(self.line.as_bytes().iter().cloned());
+        self.out.push(b'\n');
+        self.line.clear()
+    }
+
+    fn block(&mut self, ops: &[Op]) {
+        for op in ops.iter() {
+            match *op {
+                Op::Loop(_, ref body) => {
+                    let nested = body.iter().any(|op| match *op {
+                        Op::Loop(..) => true,
+                        _ => false
+                    });
+                    let mut flat = String::new();
+                    flat_loop(&**body, &mut flat);
+                    self.flush();
+                    if !nested && self.indent() + flat.len() <= WIDTH {
+                        self.push(&*flat);
+                        self.flush();
+                    } else {
+                        self.push("[");
+                        self.flush();
+                        self.depth += 1;
+                        self.block(&**body);
+                        self.flush();
+                        self.depth -= 1;
+                        self.push("]");
+                        self.flush();
+                    }
+                }
+                ref op => {
+                    let mut s = String::new();
+                    s.push(command(op));
+                    self.push(&*s)
+                }
+            }
+        }
+    }
+}
@@ -0,0 +1,52 @@
+//! `bf_fmt [--minify] [<program.b>]`
+//!
+//! Pretty-print (or minify) a brainfuck program from a file, or
+//! stdin, to stdout.
+
+#![feature(env, io, path)]
+
+extern crate bf_fmt;
+
+use std::env;
+use std::old_io as io;
+
+const USAGE: &'static str = "usage: bf_fmt [--minify] [<program.b>]";
+
+fn main() {
+    let mut minify = false;
+    let mut path = None;
+    for arg in env::args().skip(1) {
+        match &*arg {
+            "-m" | "--minify" => minify = true,
+            "-h" | "--help" => return println!("{}", USAGE),
+            _ if path.is_none() => path = Some(arg.clone()),
+            _ => return fail(USAGE)
+        }
+    }
+
+    let src = match path {
+        Some(ref path) if &**path != "-" => io::File::open(&Path::new(&**path)).read_to_end(),
+        _ => io::stdin().read_to_end()
+    };
+    let src = match src {
+        Ok(src) => src,
+        Err(e) => return fail(&*format!("bf_fmt: couldn't read the program: {}", e))
+    };
+
+    let out = if minify {
+        bf_fmt::minify(&*src)
+    } else {
+        match bf_fmt::pretty(&*src) {
+            Ok(out) => out,
+            Err(e) => return fail(&*format!("bf_fmt: {}", e))
+        }
+    };
+    if let Err(e) = io::stdout().write(&*out) {
+        fail(&*format!("bf_fmt: couldn't write the output: {}", e))
+    }
+}
+
+fn fail(msg: &str) {
+    let _ = writeln!(&mut io::stderr(), "{}", msg);
+    env::set_exit_status(1)
+}
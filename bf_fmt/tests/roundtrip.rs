@@ -0,0 +1,95 @@
+//! Checks that formatting doesn't change what programs do.
+
+#![feature(io)]
+
+extern crate bf_fmt;
+extern crate bf_interpreter;
+extern crate bf_bf_interpreter;
+
+use std::old_io::{BufReader, MemWriter};
+
+use bf_fmt::{minify, pretty, Error, WIDTH};
+
+fn run(src: &[u8], input: &[u8]) -> (Vec<u8>, Vec<u8>) {
+    let program = bf_interpreter::Program::parse(src).unwrap();
+    let mut input = BufReader::new(input);
+    let mut out = MemWriter::new();
+    let tape = program.run(&mut input, &mut out).unwrap();
+    (tape, out.into_inner())
+}
+
+/// Check that `src`, minified and pretty-printed, behaves the same
+/// way as the original, and that formatting again changes nothing.
+fn roundtrip(src: &[u8], input: &[u8]) {
+    let minified = minify(src);
+    let pretty_ = pretty(src).unwrap();
+
+    let (tape, out) = run(src, input);
+    for formatted in [&minified, &pretty_].iter() {
+        let (t, o) = run(&***formatted, input);
+        assert_eq!(o, out);
+        assert!(t == tape);
+    }
+
+    assert_eq!(pretty(&*pretty_).unwrap(), pretty_);
+    assert_eq!(pretty(&*minified).unwrap(), pretty_);
+    assert_eq!(minify(&*pretty_), minified);
+    assert_eq!(minify(&*minified), minified);
+}
+
+static HELLO_WORLD: &'static [u8] =
+    b"++++++++[>++++[>++>+++>+++>+<<<<-]>+>+>->>+[<]<-]>>.>\
+      ---.+++++++..+++.>>.<-.<.+++.------.--------.>>+.>++.";
+
+#[test]
+fn minify_drops_comments() {
+    assert_eq!(minify(b"+ add one\n[-] clear it # done"), b"+[-]");
+    // the embedded input is left alone, even if it looks like code
+    assert_eq!(minify(b"read , it ,.!a [b]\n"), b",,.!a [b]\n");
+    // unmatched loops are fine
+    assert_eq!(minify(b"] nope ["), b"][");
+}
+
+#[test]
+fn pretty_layout() {
+    assert_eq!(pretty(b"+++ [ - ] ..").unwrap(), b"+++\n[-]\n..\n");
+    assert_eq!(pretty(b"").unwrap(), b"");
+    assert_eq!(pretty(b"+[>[-]<-]>.").unwrap(),
+               b"+\n[\n  >\n  [-]\n  <-\n]\n>.\n");
+    assert_eq!(pretty(b",[.,]!xy").unwrap(), b",\n[.,]\n!xy");
+    // sibling loops each get a line
+    assert_eq!(pretty(b"+[-]>[-]").unwrap(), b"+\n[-]\n>\n[-]\n");
+
+    // long stretches are wrapped
+    let long: Vec<u8> = (0..200).map(|_| b'+').collect();
+    let out = pretty(&*long).unwrap();
+    assert!(out.split(|&b| b == b'\n').all(|line| line.len() <= WIDTH));
+    assert_eq!(minify(&*out), long);
+}
+
+#[test]
+fn hello_world() {
+    roundtrip(HELLO_WORLD, b"");
+}
+
+#[test]
+fn semantics() {
+    roundtrip(b"read ,., twice +[-.,+] and echo !ab", b"cd");
+    roundtrip(b"-.+.<<+. off the left", b"");
+    roundtrip(b"+[>+]<[->>+<<]<.", b"");
+}
+
+#[test]
+fn self_interpreter() {
+    let mut input = HELLO_WORLD.to_vec();
+    input.push(b'!');
+    let src = bf_bf_interpreter::SOURCE;
+    assert_eq!(run(src, &*input).1, b"Hello World!\n");
+    roundtrip(src, &*input);
+}
+
+#[test]
+fn errors() {
+    assert_eq!(pretty(b"+[-"), Err(Error::Unclosed(1)));
+    assert_eq!(pretty(b"+]-"), Err(Error::Unmatched(1)));
+}
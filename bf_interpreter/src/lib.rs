@@ -0,0 +1,755 @@
+//! A brainfuck interpreter, for programs that are only known at
+//! runtime, with the same semantics as `brainfuck!`: 30,000 wrapping
+//! `u8` cells, `,` reading -1 at the end of the input, and the
+//! pointer clamped at the ends of the tape. Other common semantics
+//! can be chosen with a `Config`, programs can be in any of the
+//! dialects that `brainfuck!` supports, and `Program::run_profiled`
+//! counts how often each instruction and loop runs.
+
+#![crate_name="bf_interpreter"]
+#![crate_type="lib"]
+#![feature(io)]
+
+extern crate bf_ir;
+
+use std::cmp::Ordering;
+use std::collections::HashMap;
+use std::default::Default;
+use std::error::FromError;
+use std::fmt;
+use std::old_io as io;
+
+use bf_ir::Op;
+
+pub use bf_ir::{Dialect, Error};
+
+/// The number of cells on the tape.
+pub const TAPE_LEN: usize = 30_000;
+
+/// What `,` does at the end of the input.
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum Eof {
+    /// Set the cell to -1, like `brainfuck!`.
+    MinusOne,
+    /// Set the cell to 0.
+    Zero,
+    /// Leave the cell as it is.
+    Unchanged,
+}
+
+/// What happens when the pointer moves past an end of the tape.
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum Bounds {
+    /// Stop at the end, like `brainfuck!`.
+    Clamp,
+    /// Carry on from the other end.
+    Wrap,
+    /// Stop the program with `RunError::OutOfBounds`.
+    Error,
+}
+
+/// The semantics to run a program with. The default is the same as
+/// `brainfuck!`. In Boolfuck and Smallfuck, the tape is `tape_len`
+/// single bits, so `cell_bits` and `eof` don't apply, and Smallfuck
+/// programs always end when the pointer moves off the tape, so
+/// `bounds` doesn't either.
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub struct Config {
+    /// The number of cells on the tape.
+    pub tape_len: usize,
+    /// The number of bits in each cell: 8, 16 or 32. Cells wrap
+    /// around, and only the low 8 bits are written by `.`.
+    pub cell_bits: u32,
+    pub eof: Eof,
+    pub bounds: Bounds,
+    /// The most instructions to run (counting each `[` and `]` when
+    /// it's reached), or `None` for no limit.
+    pub fuel: Option<u64>,
+}
+
+impl Default for Config {
+    fn default() -> Config {
+        Config {
+            tape_len: TAPE_LEN,
+            cell_bits: 8,
+            eof: Eof::MinusOne,
+            bounds: Bounds::Clamp,
+            fuel: None,
+        }
+    }
+}
+
+/// Why a program stopped before the end.
+#[derive(Debug)]
+pub enum RunError {
+    Io(io::IoError),
+    /// The pointer moved past an end of the tape, with
+    /// `Bounds::Error`.
+    OutOfBounds,
+    /// The program used up all of its fuel.
+    OutOfFuel,
+    /// A pbrain program called a procedure that wasn't defined, under
+    /// this cell value.
+    UndefinedProc(u32),
+}
+
+impl FromError<io::IoError> for RunError {
+    fn from_error(e: io::IoError) -> RunError {
+        RunError::Io(e)
+    }
+}
+
+impl fmt::Display for RunError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            RunError::Io(ref e) => write!(f, "{}", e),
+            RunError::OutOfBounds => write!(f, "the pointer moved off the end of the tape"),
+            RunError::OutOfFuel => write!(f, "ran out of fuel"),
+            RunError::UndefinedProc(n) => write!(f, "call to undefined procedure {}", n),
+        }
+    }
+}
+
+/// Counts of what a program did, filled in even if it stops early.
+#[derive(Clone, Copy, PartialEq, Debug, Default)]
+pub struct Stats {
+    /// The number of instructions run, counted the same way as the
+    /// fuel.
+    pub steps: u64,
+    /// The number of cells from the start of the tape to the
+    /// furthest the pointer reached.
+    pub cells: usize,
+    /// The number of bytes read, including the embedded input.
+    pub input: u64,
+    /// The number of bytes written.
+    pub output: u64,
+}
+
+/// A place in the source of a program, with the line and column
+/// counted from 1.
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub struct Loc {
+    pub offset: usize,
+    pub line: usize,
+    pub col: usize,
+}
+
+impl fmt::Display for Loc {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}:{}", self.line, self.col)
+    }
+}
+
+/// How often a single instruction ran.
+#[derive(Clone, PartialEq, Debug)]
+pub struct InsnProfile {
+    /// The brainfuck the instruction runs, like `+` or `>>>`.
+    pub text: String,
+    /// Where it is, if the program was parsed from source.
+    pub loc: Option<Loc>,
+    pub count: u64,
+}
+
+/// How often a loop ran, and how much of the program's time was
+/// spent in it.
+#[derive(Clone, PartialEq, Debug)]
+pub struct LoopProfile {
+    /// The brainfuck of the whole loop, shortened if it's long.
+    pub text: String,
+    /// Where its `[` and `]` are, if the program was parsed from
+    /// source.
+    pub start: Option<Loc>,
+    pub end: Option<Loc>,
+    /// The number of times the `[` was reached (including when the
+    /// body was skipped).
+    pub entries: u64,
+    /// The number of times the body ran.
+    pub iterations: u64,
+    /// The steps spent in the loop, including its `[` and `]` and any
+    /// loops inside it.
+    pub steps: u64,
+    /// Like `steps`, but not counting the loops inside it.
+    pub own_steps: u64,
+}
+
+impl LoopProfile {
+    /// The loop's span in the source, like `12:5-14:20`, or its
+    /// brainfuck if there's no source.
+    pub fn name(&self) -> String {
+        match (self.start, self.end) {
+            (Some(start), Some(end)) => format!("{}-{}", start, end),
+            _ => self.text.clone()
+        }
+    }
+}
+
+/// Counts of how often each part of a program ran, filled in even if
+/// it stops early.
+#[derive(Clone, PartialEq, Debug, Default)]
+pub struct Profile {
+    /// Every instruction, in program order.
+    pub insns: Vec<InsnProfile>,
+    /// Every loop, in the order of their `[`s.
+    pub loops: Vec<LoopProfile>,
+}
+
+/// The most characters of a loop shown in `LoopProfile::text`.
+const LOOP_TEXT: usize = 40;
+
+impl Profile {
+    /// The total number of steps, counted the same way as
+    /// `Stats::steps`.
+    pub fn steps(&self) -> u64 {
+        self.insns.iter().fold(0, |sum, insn| sum + insn.count)
+    }
+
+    /// The `n` loops with the most `own_steps`, hottest first.
+    pub fn hottest(&self, n: usize) -> Vec<&LoopProfile> {
+        let mut loops: Vec<_> = self.loops.iter().filter(|l| l.entries > 0).collect();
+        loops.sort_by(|a, b| match b.own_steps.cmp(&a.own_steps) {
+            Ordering::Equal => b.steps.cmp(&a.steps),
+            order => order
+        });
+        loops.truncate(n);
+        loops
+    }
+
+    /// The number of steps spent on each kind of instruction (by its
+    /// `text`), most first.
+    pub fn commands(&self) -> Vec<(&str, u64)> {
+        let mut commands: Vec<(&str, u64)> = vec![];
+        for insn in self.insns.iter().filter(|insn| insn.count > 0) {
+            match commands.iter().position(|&(text, _)| text == &*insn.text) {
+                Some(i) => commands[i].1 += insn.count,
+                None => commands.push((&*insn.text, insn.count))
+            }
+        }
+        commands.sort_by(|a, b| match b.1.cmp(&a.1) {
+            Ordering::Equal => a.0.cmp(b.0),
+            order => order
+        });
+        commands
+    }
+
+    /// A text table of the steps spent on each kind of instruction
+    /// and in the `n` hottest loops.
+    pub fn table(&self, n: usize) -> String {
+        let steps = self.steps();
+        let percent = |x: u64| if steps == 0 { 0.0 } else { x as f64 * 100.0 / steps as f64 };
+
+        let mut out = format!("{} steps\n\n{:>12} {:>6}  command\n", steps, "steps", "%");
+        for &(text, count) in self.commands().iter() {
+            out.push_str(&*format!("{:>12} {:>6.2}  {}\n", count, percent(count), text))
+        }
+
+        out.push_str(&*format!("\n{:>12} {:>6} {:>12} {:>10} {:>12}  loop\n",
+                               "own steps", "%", "steps", "entries", "iterations"));
+        for l in self.hottest(n).iter() {
+            out.push_str(&*format!("{:>12} {:>6.2} {:>12} {:>10} {:>12}  {}  {}\n",
+                                   l.own_steps, percent(l.own_steps), l.steps,
+                                   l.entries, l.iterations, l.name(), l.text))
+        }
+        out
+    }
+}
+
+#[derive(Clone, Copy, PartialEq, Debug)]
+enum Insn {
+    /// Add to the cell, wrapping to its width.
+    Add(i32),
+    Move(isize),
+    In,
+    Out,
+    Clear,
+    /// `[`, and the position of its `]`.
+    Open(usize),
+    /// `]`, and the position of its `[`.
+    Close(usize),
+    /// pbrain's `(`, and the position of its `)`.
+    ProcStart(usize),
+    /// pbrain's `)`, returning from the procedure.
+    ProcEnd,
+    Call,
+    Fork,
+    End,
+    Store,
+    Load,
+    ShiftRight,
+    ShiftLeft,
+    Not,
+    Flip,
+    /// Boolfuck's `,` and `;`, which read and write single bits.
+    InBit,
+    OutBit,
+}
+
+fn flatten(ops: &[Op], bits: bool, insns: &mut Vec<Insn>) {
+    for op in ops.iter() {
+        let insn = match *op {
+            Op::Add(n) => Insn::Add(n),
+            Op::Move(n) => Insn::Move(n),
+            Op::In if bits => Insn::InBit,
+            Op::Out if bits => Insn::OutBit,
+            Op::In => Insn::In,
+            Op::Out => Insn::Out,
+            Op::Clear => Insn::Clear,
+            Op::Call => Insn::Call,
+            Op::Fork => Insn::Fork,
+            Op::End => Insn::End,
+            Op::Store => Insn::Store,
+            Op::Load => Insn::Load,
+            Op::ShiftRight => Insn::ShiftRight,
+            Op::ShiftLeft => Insn::ShiftLeft,
+            Op::Not => Insn::Not,
+            Op::Flip => Insn::Flip,
+            // multiplication loops and scans are just run as loops
+            Op::Loop(_, ref body) | Op::Mul(_, _, ref body) => {
+                flatten_loop(&**body, bits, insns);
+                continue
+            }
+            Op::Scan(n) => {
+                flatten_loop(&[Op::Move(n)], bits, insns);
+                continue
+            }
+            Op::Proc(_, ref body) => {
+                let start = insns.len();
+                insns.push(Insn::ProcStart(0));
+                flatten(&**body, bits, insns);
+                insns[start] = Insn::ProcStart(insns.len());
+                insns.push(Insn::ProcEnd);
+                continue
+            }
+            Op::Debug(_) => continue,
+        };
+        insns.push(insn)
+    }
+}
+
+fn flatten_loop(body: &[Op], bits: bool, insns: &mut Vec<Insn>) {
+    let open = insns.len();
+    insns.push(Insn::Open(0));
+    flatten(body, bits, insns);
+    let close = insns.len();
+    insns[open] = Insn::Open(close);
+    insns.push(Insn::Close(open))
+}
+
+fn repeat(c: char, n: usize) -> String {
+    let mut s = String::new();
+    for _ in 0..n {
+        s.push(c)
+    }
+    s
+}
+
+impl Insn {
+    /// The source that this runs, in `dialect`.
+    fn text(&self, dialect: Dialect) -> String {
+        let c = match *self {
+            Insn::Add(n) if n < 0 => return repeat('-', -n as usize),
+            Insn::Add(n) => return repeat('+', n as usize),
+            Insn::Move(n) if n < 0 => return repeat('<', -n as usize),
+            Insn::Move(n) => return repeat('>', n as usize),
+            Insn::Clear => return "[-]".to_string(),
+            Insn::In | Insn::InBit => ',',
+            Insn::Out => '.',
+            Insn::OutBit => ';',
+            Insn::Open(_) => '[',
+            Insn::Close(_) => ']',
+            Insn::ProcStart(_) => '(',
+            Insn::ProcEnd => ')',
+            Insn::Call => ':',
+            Insn::Fork => 'Y',
+            Insn::End => '@',
+            Insn::Store => '$',
+            Insn::Load => '!',
+            Insn::ShiftRight => '}',
+            Insn::ShiftLeft => '{',
+            Insn::Not => '~',
+            Insn::Flip if dialect == Dialect::Smallfuck => '*',
+            Insn::Flip => '+',
+        };
+        c.to_string()
+    }
+}
+
+/// Move the pointer `i` by `n` on a tape of `len` cells, going past
+/// the ends according to `bounds`.
+fn move_by(i: usize, n: isize, len: usize, bounds: Bounds) -> Result<usize, RunError> {
+    if n < 0 {
+        let n = -n as usize;
+        if i >= n {
+            return Ok(i - n)
+        }
+        match bounds {
+            Bounds::Clamp => Ok(0),
+            Bounds::Wrap => Ok((i + len - n % len) % len),
+            Bounds::Error => Err(RunError::OutOfBounds)
+        }
+    } else {
+        let n = n as usize;
+        if i + n < len {
+            return Ok(i + n)
+        }
+        match bounds {
+            Bounds::Clamp => Ok(len - 1),
+            Bounds::Wrap => Ok((i + n) % len),
+            Bounds::Error => Err(RunError::OutOfBounds)
+        }
+    }
+}
+
+/// Read the next byte of `input`, or from `r` once that's empty,
+/// giving `None` at the end.
+fn read_byte(input: &mut &[u8], r: &mut Reader) -> Result<Option<u8>, RunError> {
+    if !input.is_empty() {
+        let b = input[0];
+        *input = &input[1..];
+        return Ok(Some(b))
+    }
+    match r.read_byte() {
+        Ok(b) => Ok(Some(b)),
+        Err(io::IoError { kind: io::EndOfFile, .. }) => Ok(None),
+        Err(e) => Err(RunError::Io(e))
+    }
+}
+
+/// One thread of a running program. Only Brainfork programs ever
+/// have more than one.
+struct Thread {
+    /// The next instruction.
+    pc: usize,
+    /// The index of its current cell.
+    i: usize,
+    /// Where to return to from each pbrain procedure it's in.
+    calls: Vec<usize>,
+    /// Whether it's the thread the program started with, whose
+    /// pointer is the one kept in the `State`.
+    main: bool,
+}
+
+/// The tape and the pointer, which can be kept from one run to the
+/// next (like in a REPL).
+#[derive(Clone, PartialEq, Debug)]
+pub struct State {
+    pub tape: Vec<u32>,
+    /// The index of the current cell.
+    pub pointer: usize,
+}
+
+impl State {
+    /// An empty tape for `config`, with the pointer at the start.
+    pub fn new(config: &Config) -> State {
+        assert!(config.tape_len > 0, "bf_interpreter: the tape can't be empty");
+        State { tape: vec![0; config.tape_len], pointer: 0 }
+    }
+}
+
+pub struct Program {
+    insns: Vec<Insn>,
+    /// The input embedded after the first `!`.
+    input: Vec<u8>,
+    /// Where each instruction is in the source, if the program was
+    /// parsed from it (otherwise empty).
+    locs: Vec<Loc>,
+    dialect: Dialect,
+}
+
+impl Program {
+    /// Parse `src`, where every byte other than the eight commands
+    /// is a comment, and everything after the first `!` is input
+    /// that is read before anything from the real reader (just like
+    /// a string literal given to `brainfuck!`).
+    pub fn parse(src: &[u8]) -> Result<Program, Error> {
+        Program::parse_dialect(src, Dialect::Brainfuck)
+    }
+
+    /// Parse `src` as a program in `dialect`, like `parse` (except
+    /// that in Extended Brainfuck, `!` is a command, so there's no
+    /// embedded input).
+    pub fn parse_dialect(src: &[u8], dialect: Dialect) -> Result<Program, Error> {
+        let program = try!(bf_ir::parse_dialect(src, dialect));
+        let mut program = Program::from_ir_dialect(program, dialect);
+
+        // without optimising, each command is a single instruction, in
+        // the same order
+        let (mut line, mut col) = (1, 1);
+        let separator = dialect.input_separator();
+        for (offset, &b) in src.iter().take_while(|&&b| !separator || b != b'!').enumerate() {
+            if dialect.is_command(b as char) {
+                program.locs.push(Loc { offset: offset, line: line, col: col })
+            }
+            if b == b'\n' {
+                line += 1;
+                col = 1
+            } else {
+                col += 1
+            }
+        }
+        debug_assert_eq!(program.locs.len(), program.insns.len());
+        Ok(program)
+    }
+
+    /// Prepare a program for running, which can only contain the
+    /// plain brainfuck ops and those from `bf_ir::opt` (any `Debug`s
+    /// are ignored). The passes leave each `Add` unwrapped, so an
+    /// optimised program runs the same with any `cell_bits`, but IR
+    /// from elsewhere has to do the same: an `Add(255)` standing in
+    /// for `-` is only right with 8-bit cells.
+    pub fn from_ir(program: bf_ir::Program) -> Program {
+        Program::from_ir_dialect(program, Dialect::Brainfuck)
+    }
+
+    /// Like `from_ir`, for a program in `dialect`.
+    pub fn from_ir_dialect(program: bf_ir::Program, dialect: Dialect) -> Program {
+        let mut insns = vec![];
+        flatten(&*program.ops, dialect.bits(), &mut insns);
+        Program { insns: insns, input: program.input, locs: vec![], dialect: dialect }
+    }
+
+    /// Run the program, returning the final tape, just like the
+    /// functions generated by `brainfuck!` (so in Boolfuck and
+    /// Smallfuck, the bits are packed eight to a byte, least
+    /// significant first).
+    pub fn run(&self, r: &mut Reader, w: &mut Writer) -> io::IoResult<Vec<u8>> {
+        match self.run_with(&Default::default(), &mut Default::default(), r, w) {
+            Ok(ref tape) if self.dialect.bits() => {
+                Ok(tape.chunks(8).map(|bits| {
+                    bits.iter().rev().fold(0, |byte, &bit| byte << 1 | bit as u8)
+                }).collect())
+            }
+            Ok(tape) => Ok(tape.into_iter().map(|c| c as u8).collect()),
+            Err(RunError::Io(e)) => Err(e),
+            Err(RunError::UndefinedProc(n)) => Err(io::IoError {
+                kind: io::OtherIoError,
+                desc: "pbrain: call to an undefined procedure",
+                detail: Some(format!("procedure {}", n))
+            }),
+            Err(e) => panic!("bf_interpreter: {} with the default config", e)
+        }
+    }
+
+    /// Run the program with the semantics in `config`, returning the
+    /// final tape, and recording what it did in `stats`.
+    pub fn run_with(&self, config: &Config, stats: &mut Stats,
+                    r: &mut Reader, w: &mut Writer) -> Result<Vec<u32>, RunError> {
+        let mut state = State::new(config);
+        try!(self.run_on(&mut state, config, stats, r, w));
+        Ok(state.tape)
+    }
+
+    /// Run the program starting from `state` (which has to have been
+    /// created with the same `config`), leaving it as the program
+    /// left it, even if it stops early.
+    pub fn run_on(&self, state: &mut State, config: &Config, stats: &mut Stats,
+                  r: &mut Reader, w: &mut Writer) -> Result<(), RunError> {
+        self.run_counting(state, config, stats, None, r, w)
+    }
+
+    /// Like `run_with`, but also counting how often each instruction
+    /// and loop runs, in `profile`.
+    pub fn run_profiled(&self, config: &Config, stats: &mut Stats, profile: &mut Profile,
+                        r: &mut Reader, w: &mut Writer) -> Result<Vec<u32>, RunError> {
+        let mut state = State::new(config);
+        let mut counts = vec![0; self.insns.len()];
+        let result = self.run_counting(&mut state, config, stats, Some(&mut *counts), r, w);
+        *profile = self.profile(&*counts);
+        try!(result);
+        Ok(state.tape)
+    }
+
+    /// Put `counts` (the number of times each instruction ran)
+    /// together with the source of each instruction and loop.
+    fn profile(&self, counts: &[u64]) -> Profile {
+        let loc = |i: usize| self.locs.get(i).cloned();
+        let insns = self.insns.iter().zip(counts.iter()).enumerate().map(|(i, (insn, &count))| {
+            InsnProfile { text: insn.text(self.dialect), loc: loc(i), count: count }
+        }).collect();
+
+        let mut loops: Vec<LoopProfile> = vec![];
+        // the index in `loops` of each enclosing loop, along with the
+        // steps spent in the loops directly inside it
+        let mut enclosing: Vec<(usize, u64)> = vec![];
+        for (open, insn) in self.insns.iter().enumerate() {
+            match *insn {
+                Insn::Open(close) => {
+                    let steps = counts[open..close + 1].iter().fold(0, |sum, &n| sum + n);
+                    let mut text = String::new();
+                    for insn in self.insns[open..close + 1].iter() {
+                        text.push_str(&*insn.text(self.dialect));
+                        if text.len() > LOOP_TEXT {
+                            text.truncate(LOOP_TEXT - 3);
+                            text.push_str("...");
+                            break
+                        }
+                    }
+                    enclosing.push((loops.len(), 0));
+                    loops.push(LoopProfile {
+                        text: text,
+                        start: loc(open),
+                        end: loc(close),
+                        entries: counts[open],
+                        iterations: counts[close],
+                        steps: steps,
+                        own_steps: steps,
+                    })
+                }
+                Insn::Close(_) => {
+                    let (i, inner) = enclosing.pop().unwrap();
+                    loops[i].own_steps -= inner;
+                    if let Some(outer) = enclosing.last_mut() {
+                        outer.1 += loops[i].steps
+                    }
+                }
+                _ => {}
+            }
+        }
+        Profile { insns: insns, loops: loops }
+    }
+
+    /// Run the program, adding the number of times each instruction
+    /// runs to `counts`, if there is one. Brainfork's threads each
+    /// run one instruction in turn, in the order they were created,
+    /// just like `brainfuck!`.
+    fn run_counting(&self, state: &mut State, config: &Config, stats: &mut Stats,
+                    mut counts: Option<&mut [u64]>,
+                    r: &mut Reader, w: &mut Writer) -> Result<(), RunError> {
+        assert!(config.cell_bits == 8 || config.cell_bits == 16 || config.cell_bits == 32,
+                "bf_interpreter: cells can't have {} bits", config.cell_bits);
+        assert!(state.tape.len() == config.tape_len,
+                "bf_interpreter: the tape doesn't match the config");
+        let mask = (1i64 << config.cell_bits) - 1;
+        let len = config.tape_len;
+
+        let State { ref mut tape, ref mut pointer } = *state;
+        let mut input = &*self.input;
+        // pbrain's procedures, by the value they were defined under,
+        // and Extended Brainfuck's register
+        let mut procs = HashMap::new();
+        let mut register = 0;
+        // the bits of the last byte read that haven't been used yet,
+        // and the bits written that don't make up a whole byte yet,
+        // for Boolfuck
+        let (mut in_byte, mut in_bits) = (0u8, 0);
+        let (mut out_byte, mut out_bits) = (0u8, 0);
+
+        let mut threads = vec![Thread { pc: 0, i: *pointer, calls: vec![], main: true }];
+        let mut t = 0;
+        *stats = Stats { cells: *pointer + 1, ..Default::default() };
+        while !threads.is_empty() {
+            if t == threads.len() {
+                t = 0
+            }
+            let (pc, mut i) = (threads[t].pc, threads[t].i);
+            if pc == self.insns.len() {
+                // the thread has finished, and the next one runs in
+                // its place
+                threads.remove(t);
+                continue
+            }
+            if Some(stats.steps) == config.fuel {
+                return Err(RunError::OutOfFuel)
+            }
+            stats.steps += 1;
+            if let Some(ref mut counts) = counts {
+                counts[pc] += 1
+            }
+
+            let mut next = pc + 1;
+            match self.insns[pc] {
+                Insn::Add(d) => tape[i] = ((tape[i] as i64 + d as i64) & mask) as u32,
+                Insn::Move(n) if self.dialect == Dialect::Smallfuck => {
+                    match move_by(i, n, len, Bounds::Error) {
+                        Ok(j) => i = j,
+                        Err(_) => return Ok(())
+                    }
+                }
+                Insn::Move(n) => i = try!(move_by(i, n, len, config.bounds)),
+                Insn::In => match (try!(read_byte(&mut input, r)), config.eof) {
+                    (Some(b), _) => {
+                        stats.input += 1;
+                        tape[i] = b as u32
+                    }
+                    (None, Eof::MinusOne) => tape[i] = mask as u32,
+                    (None, Eof::Zero) => tape[i] = 0,
+                    (None, Eof::Unchanged) => {}
+                },
+                Insn::Out => {
+                    try!(w.write(&[tape[i] as u8]));
+                    stats.output += 1;
+                }
+                Insn::Clear => tape[i] = 0,
+                Insn::Open(close) => if tape[i] == 0 { next = close + 1 },
+                Insn::Close(open) => if tape[i] != 0 { next = open + 1 },
+                Insn::ProcStart(end) => {
+                    procs.insert(tape[i], pc + 1);
+                    next = end + 1
+                }
+                Insn::ProcEnd => next = threads[t].calls.pop().unwrap(),
+                Insn::Call => match procs.get(&tape[i]) {
+                    Some(&start) => {
+                        threads[t].calls.push(pc + 1);
+                        next = start
+                    }
+                    None => return Err(RunError::UndefinedProc(tape[i]))
+                },
+                Insn::Fork => {
+                    // the child starts at the next instruction, one
+                    // cell to the right, and takes its first step in
+                    // this round
+                    let child = try!(move_by(i, 1, len, config.bounds));
+                    tape[i] = 0;
+                    tape[child] = 1;
+                    let calls = threads[t].calls.clone();
+                    threads.push(Thread { pc: pc + 1, i: child, calls: calls, main: false });
+                    if child >= stats.cells { stats.cells = child + 1 }
+                }
+                Insn::End => return Ok(()),
+                Insn::Store => register = tape[i],
+                Insn::Load => tape[i] = register,
+                Insn::ShiftRight => tape[i] >>= 1,
+                Insn::ShiftLeft => tape[i] = ((tape[i] as i64) << 1 & mask) as u32,
+                Insn::Not => tape[i] = (!tape[i] as i64 & mask) as u32,
+                Insn::Flip => tape[i] ^= 1,
+                Insn::InBit => {
+                    if in_bits == 0 {
+                        // the end of the input reads as zeros
+                        in_byte = match try!(read_byte(&mut input, r)) {
+                            Some(b) => {
+                                stats.input += 1;
+                                b
+                            }
+                            None => 0
+                        };
+                        in_bits = 8
+                    }
+                    tape[i] = (in_byte & 1) as u32;
+                    in_byte >>= 1;
+                    in_bits -= 1
+                }
+                Insn::OutBit => {
+                    out_byte |= (tape[i] as u8 & 1) << out_bits;
+                    out_bits += 1;
+                    if out_bits == 8 {
+                        try!(w.write(&[out_byte]));
+                        stats.output += 1;
+                        out_byte = 0;
+                        out_bits = 0
+                    }
+                }
+            }
+
+            threads[t].pc = next;
+            threads[t].i = i;
+            if threads[t].main { *pointer = i }
+            if i >= stats.cells { stats.cells = i + 1 }
+            t += 1;
+        }
+        // a partial byte is padded with zeros
+        if out_bits > 0 {
+            try!(w.write(&[out_byte]));
+            stats.output += 1;
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,63 @@
+//! Checks that the interpreter agrees with `brainfuck!` on the same
+//! programs, for both the output and the final tape.
+
+#![feature(plugin, io)]
+
+#[plugin] extern crate brainfuck_macros;
+extern crate bf_interpreter;
+
+use std::old_io as io;
+use std::old_io::{BufReader, MemWriter};
+
+use bf_interpreter::{Program, Error};
+
+/// Run `bf` on `input`, returning the tape and the output.
+fn run<F>(bf: F, input: &str) -> (Vec<u8>, Vec<u8>)
+    where F: FnOnce(&mut Reader, &mut Writer) -> io::IoResult<Vec<u8>>
+{
+    let mut input = BufReader::new(input.as_bytes());
+    let mut out = MemWriter::new();
+
+    let tape = bf(&mut input, &mut out).unwrap();
+    (tape, out.into_inner())
+}
+
+/// Define a test that runs the string literal `$prog` through both
+/// the macro and the interpreter, checking they agree and that the
+/// output is `$expected`.
+macro_rules! agree {
+    ($name: ident, $prog: tt, $input: expr, $expected: expr) => {
+        #[test]
+        fn $name() {
+            let (macro_tape, macro_out) = run(brainfuck!($prog), $input);
+
+            let program = Program::parse($prog.as_bytes()).unwrap();
+            let (tape, out) = run(|r, w| program.run(r, w), $input);
+
+            assert_eq!(out, macro_out);
+            assert!(tape == macro_tape);
+            assert_eq!(&*out, &$expected[..]);
+        }
+    }
+}
+
+agree!(hello_world,
+       "++++++++[>++++[>++>+++>+++>+<<<<-]>+>+>->>+[<]<-]>>.>\
+        ---.+++++++..+++.>>.<-.<.+++.------.--------.>>+.>++.",
+       "", b"Hello World!\n");
+agree!(comments, "one +. two +.", "", [1, 2]);
+agree!(cat, ",+[-.,+]", "Hello", b"Hello");
+agree!(eof, ",.", "", [255]);
+agree!(wrapping, "-.+.", "", [255, 0]);
+agree!(left_clamp, "<<+.", "", [1]);
+// fills the tape with 1s, until the last cell wraps around to 0
+agree!(right_clamp, "+[>+]<.", "", [1]);
+agree!(embedded_input, ",+[-.,+]!Hello, ", "world", b"Hello, world");
+
+#[test]
+fn errors() {
+    assert_eq!(Program::parse(b"+[[]").err(), Some(Error::Unclosed(1)));
+    assert_eq!(Program::parse(b"+]").err(), Some(Error::Unmatched(1)));
+    // everything after the `!` is input
+    assert!(Program::parse(b"[]!]").is_ok());
+}
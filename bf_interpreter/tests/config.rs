@@ -0,0 +1,120 @@
+//! Checks the semantics that can be changed with a `Config`.
+
+#![feature(io)]
+
+extern crate bf_interpreter;
+extern crate bf_ir;
+
+use std::default::Default;
+use std::old_io::{BufReader, MemWriter};
+
+use bf_interpreter::{Bounds, Config, Eof, Program, RunError, Stats};
+
+/// Run `src` on `input` with `config`, returning the tape, the output
+/// and the stats.
+fn run(src: &str, input: &str, config: Config) -> (Result<Vec<u32>, RunError>, Vec<u8>, Stats) {
+    let program = Program::parse(src.as_bytes()).unwrap();
+    let mut input = BufReader::new(input.as_bytes());
+    let mut out = MemWriter::new();
+    let mut stats = Stats::default();
+    let tape = program.run_with(&config, &mut stats, &mut input, &mut out);
+    (tape, out.into_inner(), stats)
+}
+
+fn config() -> Config {
+    Default::default()
+}
+
+#[test]
+fn eof() {
+    let tape = |eof| run("+,", "", Config { eof: eof, ..config() }).0.unwrap()[0];
+    assert_eq!(tape(Eof::MinusOne), 255);
+    assert_eq!(tape(Eof::Zero), 0);
+    assert_eq!(tape(Eof::Unchanged), 1);
+}
+
+#[test]
+fn cell_width() {
+    let tape = |bits| run("-", "", Config { cell_bits: bits, ..config() }).0.unwrap()[0];
+    assert_eq!(tape(8), 0xff);
+    assert_eq!(tape(16), 0xffff);
+    assert_eq!(tape(32), 0xffff_ffff);
+
+    // only the low byte is written
+    let (_, out, _) = run("-.,.", "", Config { cell_bits: 16, ..config() });
+    assert_eq!(out, [255, 255]);
+    // 256 doesn't wrap to 0
+    let src = "++++++++++++++++[>++++++++++++++++<-]>[-[-]>+<]>.";
+    assert_eq!(run(src, "", Config { cell_bits: 16, ..config() }).1, [1]);
+    assert_eq!(run(src, "", config()).1, [0]);
+}
+
+#[test]
+fn optimised_cell_width() {
+    // the optimisation passes don't wrap to 8 bits, so combined adds
+    // and multiplication loops are right for wider cells too
+    let mut src = b"++[->".to_vec();
+    src.extend((0..300).map(|_| b'+'));
+    src.extend(b"<]>".iter().cloned());
+    let ir = bf_ir::parse(&*src).unwrap();
+    let ops = bf_ir::opt::optimise(ir.ops, &*bf_ir::opt::passes(3), false);
+    let program = Program::from_ir(bf_ir::Program { ops: ops, input: vec![] });
+
+    let tape = |bits| {
+        let config = Config { cell_bits: bits, ..config() };
+        program.run_with(&config, &mut Stats::default(),
+                         &mut BufReader::new(b""), &mut MemWriter::new()).unwrap()[1]
+    };
+    assert_eq!(tape(8), 600 % 256);
+    assert_eq!(tape(16), 600);
+}
+
+#[test]
+fn bounds() {
+    let small = Config { tape_len: 3, ..config() };
+    let (tape, _, _) = run("<+>>>>>+", "", small);
+    assert_eq!(tape.unwrap(), [1, 0, 1]);
+
+    let (tape, _, _) = run("<+>>>>>+", "", Config { bounds: Bounds::Wrap, ..small });
+    assert_eq!(tape.unwrap(), [0, 1, 1]);
+
+    match run(">>>", "", Config { bounds: Bounds::Error, ..small }).0 {
+        Ok(_) => panic!("should be out of bounds"),
+        Err(e) => assert!(match e { RunError::OutOfBounds => true, _ => false })
+    }
+    assert!(run(">><<", "", Config { bounds: Bounds::Error, ..small }).0.is_ok());
+    assert!(run("<", "", Config { bounds: Bounds::Error, ..small }).0.is_err());
+}
+
+#[test]
+fn fuel() {
+    // `+[]` never stops
+    let (tape, _, stats) = run("+[]", "", Config { fuel: Some(100), ..config() });
+    assert!(match tape { Err(RunError::OutOfFuel) => true, _ => false });
+    assert_eq!(stats.steps, 100);
+
+    assert!(run("+++", "", Config { fuel: Some(3), ..config() }).0.is_ok());
+    assert!(run("++++", "", Config { fuel: Some(3), ..config() }).0.is_err());
+}
+
+#[test]
+fn stats() {
+    let (_, _, stats) = run(",[.,]>>>!ab", "c", Config { eof: Eof::Zero, ..config() });
+    assert_eq!(stats, Stats { steps: 14, cells: 4, input: 3, output: 3 });
+}
+
+#[test]
+fn state() {
+    // the tape and pointer carry over from one program to the next
+    let config = config();
+    let mut state = bf_interpreter::State::new(&config);
+    let mut out = MemWriter::new();
+    for src in ["++>+++", "<[->+<]>.", ">>>>"].iter() {
+        let program = Program::parse(src.as_bytes()).unwrap();
+        program.run_on(&mut state, &config, &mut Stats::default(),
+                       &mut BufReader::new(b""), &mut out).unwrap();
+    }
+    assert_eq!(out.into_inner(), [5]);
+    assert_eq!(&state.tape[..3], [0, 5, 0]);
+    assert_eq!(state.pointer, 5);
+}
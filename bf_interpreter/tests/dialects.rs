@@ -0,0 +1,130 @@
+//! Checks that the interpreter agrees with `brainfuck!` on programs
+//! in each of the other dialects, for both the output and the final
+//! tape.
+
+#![feature(plugin, io)]
+
+#[plugin] extern crate brainfuck_macros;
+extern crate bf_interpreter;
+
+use std::default::Default;
+use std::old_io as io;
+use std::old_io::{BufReader, MemWriter};
+
+use bf_interpreter::{Bounds, Config, Dialect, Program, RunError, Stats};
+
+/// Run `bf` on `input`, returning the tape and the output.
+fn run<F>(bf: F, input: &[u8]) -> io::IoResult<(Vec<u8>, Vec<u8>)>
+    where F: FnOnce(&mut Reader, &mut Writer) -> io::IoResult<Vec<u8>>
+{
+    let mut input = BufReader::new(input);
+    let mut out = MemWriter::new();
+
+    let tape = try!(bf(&mut input, &mut out));
+    Ok((tape, out.into_inner()))
+}
+
+/// Define a test that runs the string literal `$prog` in the dialect
+/// `$name` (`Dialect::$dialect`) through both the macro and the
+/// interpreter, checking they agree and that the output is
+/// `$expected`.
+macro_rules! agree {
+    ($test: ident, $name: tt, $dialect: ident, $prog: tt, $input: expr, $expected: expr) => {
+        #[test]
+        fn $test() {
+            let (macro_tape, macro_out) = run(brainfuck!(dialect = $name; $prog), $input).unwrap();
+
+            let program = Program::parse_dialect($prog.as_bytes(), Dialect::$dialect).unwrap();
+            let (tape, out) = run(|r, w| program.run(r, w), $input).unwrap();
+
+            assert_eq!(out, macro_out);
+            assert!(tape == macro_tape);
+            assert_eq!(&*out, &$expected[..]);
+        }
+    }
+}
+
+agree!(pbrain_call, pbrain, Pbrain, "+(>++++++++[<++++++++>-]<.): [-]+:", b"", b"AA");
+agree!(pbrain_recursive, pbrain, Pbrain, "++() [-]+ (>++++++[>+++++++<-]>++++.[-]<<,:) :",
+       b"\x01\x01\x02", b"...");
+// the second definition replaces the first
+agree!(pbrain_redefine, pbrain, Pbrain, "(+++++.)(++.):", b"", [2]);
+agree!(brainfork_fork, brainfork, Brainfork, "+++Y[.-]", b"", [1]);
+agree!(brainfork_round_robin, brainfork, Brainfork, "Y+++[.-]", b"", [4, 3, 3, 2, 2, 1, 1]);
+agree!(brainfork_shared_tape, brainfork, Brainfork, "+>+<Y>[]<-.", b"", [0, 255]);
+agree!(ebf1_commands, ebf1, Ebf1, "++++++++{{{+.}}}.$>!.~.@.", b"", [65, 8, 8, 247]);
+agree!(boolfuck_output, boolfuck, Boolfuck, "+;>;;;;;<;>;", b"", b"A");
+agree!(boolfuck_partial_byte, boolfuck, Boolfuck, "+;", b"", [1]);
+agree!(boolfuck_input, boolfuck, Boolfuck, ",;,;,;,;,;,;,;,;", b"hi", b"h");
+agree!(boolfuck_eof, boolfuck, Boolfuck, "+,;", b"", [0]);
+agree!(boolfuck_embedded_input, boolfuck, Boolfuck, ",;,;,;,;,;,;,;,;!h", b"", b"h");
+agree!(smallfuck, smallfuck, Smallfuck, "*>*>*<<[>]*", b"", []);
+agree!(smallfuck_left_edge, smallfuck, Smallfuck, "*<*", b"", []);
+agree!(smallfuck_right_edge, smallfuck, Smallfuck, "*[>*]", b"", []);
+
+#[test]
+fn pbrain_undefined() {
+    let program = Program::parse_dialect(b"++(+)+:", Dialect::Pbrain).unwrap();
+    let err = run(|r, w| program.run(r, w), b"").err().unwrap();
+    let macro_err = run(brainfuck!(dialect = pbrain; "++(+)+:"), b"").err().unwrap();
+    assert_eq!(err.desc, macro_err.desc);
+    assert_eq!(err.detail, Some("procedure 3".to_string()));
+    assert_eq!(err.detail, macro_err.detail);
+
+    let mut stats = Stats::default();
+    let result = program.run_with(&Config::default(), &mut stats,
+                                  &mut BufReader::new(b""), &mut MemWriter::new());
+    match result {
+        Err(RunError::UndefinedProc(3)) => {}
+        r => panic!("expected an undefined procedure, found {:?}", r)
+    }
+}
+
+#[test]
+fn bits_tape() {
+    // each cell is a bit, so the tape is `tape_len` bits long
+    let program = Program::parse_dialect(b"*>*>*<<[>]*", Dialect::Smallfuck).unwrap();
+    let mut stats = Stats::default();
+    let tape = program.run_with(&Config::default(), &mut stats,
+                                &mut BufReader::new(b""), &mut MemWriter::new()).unwrap();
+    assert_eq!(&tape[..5], [1, 1, 1, 1, 0]);
+    assert_eq!(tape.len(), 30_000);
+
+    let (tape, _) = run(|r, w| program.run(r, w), b"").unwrap();
+    assert_eq!(tape.len(), 30_000 / 8);
+    assert_eq!(tape[0], 15);
+}
+
+#[test]
+fn smallfuck_edges() {
+    // moving off either end of the tape ends the program, rather than
+    // clamping, so the second `*` never runs, and the loop only ends
+    // once every bit is set
+    let left = Program::parse_dialect(b"*<*", Dialect::Smallfuck).unwrap();
+    let (tape, _) = run(|r, w| left.run(r, w), b"").unwrap();
+    assert_eq!(tape[0], 1);
+
+    let right = Program::parse_dialect(b"*[>*]", Dialect::Smallfuck).unwrap();
+    let (tape, _) = run(|r, w| right.run(r, w), b"").unwrap();
+    assert!(tape.iter().all(|&b| b == 0xff));
+
+    // whatever the config says
+    let config = Config { bounds: Bounds::Wrap, ..Default::default() };
+    let tape = left.run_with(&config, &mut Stats::default(),
+                             &mut BufReader::new(b""), &mut MemWriter::new()).unwrap();
+    assert_eq!(tape[0], 1);
+}
+
+#[test]
+fn threads_stats() {
+    // every thread's instructions count (the fork, then the parent
+    // loops 3 times and the child 4), and the child moves the
+    // furthest
+    let program = Program::parse_dialect(b"Y+++[.-]", Dialect::Brainfork).unwrap();
+    let mut stats = Stats::default();
+    program.run_with(&Config::default(), &mut stats,
+                     &mut BufReader::new(b""), &mut MemWriter::new()).unwrap();
+    assert_eq!(stats.cells, 2);
+    assert_eq!(stats.output, 7);
+    assert_eq!(stats.steps, 1 + (4 + 3 * 3) + (4 + 4 * 3));
+}
@@ -0,0 +1,92 @@
+//! Checks the counts from `Program::run_profiled`.
+
+#![feature(io)]
+
+extern crate bf_interpreter;
+extern crate bf_bf_interpreter;
+
+use std::default::Default;
+use std::old_io::{BufReader, MemWriter};
+
+use bf_interpreter::{Config, Loc, Profile, Program, RunError, Stats};
+
+fn profile(src: &[u8], input: &[u8], config: Config) -> (Result<Vec<u32>, RunError>, Vec<u8>,
+                                                         Stats, Profile) {
+    let program = Program::parse(src).unwrap();
+    let mut input = BufReader::new(input);
+    let mut out = MemWriter::new();
+    let mut stats = Stats::default();
+    let mut profile = Profile::default();
+    let tape = program.run_profiled(&config, &mut stats, &mut profile, &mut input, &mut out);
+    (tape, out.into_inner(), stats, profile)
+}
+
+#[test]
+fn counts() {
+    let src = b"++ two\n[>+++\n[->+<]<-]\n>>.";
+    let (tape, out, stats, profile) = profile(src, b"", Default::default());
+    assert_eq!(&tape.unwrap()[..3], [0, 0, 6]);
+    assert_eq!(out, [6]);
+    assert_eq!(profile.steps(), stats.steps);
+
+    // one per command, with where it is
+    assert_eq!(profile.insns.len(), 19);
+    assert_eq!(profile.insns[0].text, "+");
+    assert_eq!(profile.insns[0].count, 1);
+    assert_eq!(profile.insns[2].loc, Some(Loc { offset: 7, line: 2, col: 1 }));
+    assert_eq!(profile.insns[2].count, 1);
+    // `->+<` in the inner loop
+    assert_eq!(profile.insns[8].count, 6);
+    assert_eq!(profile.commands()[0], ("+", 14));
+
+    assert_eq!(profile.loops.len(), 2);
+    let (outer, inner) = (&profile.loops[0], &profile.loops[1]);
+    assert_eq!(outer.text, "[>+++[->+<]<-]");
+    assert_eq!(outer.name(), "2:1-3:9");
+    assert_eq!((outer.entries, outer.iterations), (1, 2));
+    assert_eq!(inner.name(), "3:1-3:6");
+    assert_eq!((inner.entries, inner.iterations), (2, 6));
+    assert_eq!(inner.steps, inner.own_steps);
+    assert_eq!(outer.steps, outer.own_steps + inner.steps);
+
+    let hottest = profile.hottest(1);
+    assert_eq!(hottest.len(), 1);
+    assert_eq!(hottest[0].name(), "3:1-3:6");
+
+    let table = profile.table(10);
+    assert!(table.starts_with(&*format!("{} steps\n", stats.steps)));
+    assert!(table.contains("3:1-3:6  [->+<]\n"));
+}
+
+#[test]
+fn stops_early() {
+    // the profile is still filled in, up to where it stopped
+    let config = Config { fuel: Some(100), ..Default::default() };
+    let (tape, _, stats, profile) = profile(b"+[>+<]", b"", config);
+    match tape {
+        Err(RunError::OutOfFuel) => {}
+        result => panic!("unexpected result {:?}", result)
+    }
+    assert_eq!(profile.steps(), stats.steps);
+    assert_eq!(profile.loops[0].entries, 1);
+    assert!(profile.loops[0].iterations > 20);
+}
+
+#[test]
+fn self_interpreter() {
+    let src = bf_bf_interpreter::SOURCE;
+    let input = b"++++++++[>++++[>++>+++>+++>+<<<<-]>+>+>->>+[<]<-]>>.>\
+                  ---.+++++++..+++.>>.<-.<.+++.------.--------.>>+.>++.!";
+    let (_, out, stats, profile) = profile(src, input, Default::default());
+    assert_eq!(out, b"Hello World!\n");
+    assert_eq!(profile.steps(), stats.steps);
+
+    // the loops are named by their lines in the source
+    let hottest = profile.hottest(3);
+    assert_eq!(hottest[0].name(), "223:3-223:11");
+    assert_eq!(hottest[0].text, "[<+>>+<-]");
+    assert!(hottest.iter().all(|l| l.own_steps <= hottest[0].own_steps));
+    // the outermost loop holds nearly everything
+    let most = profile.loops.iter().map(|l| l.steps).max().unwrap();
+    assert!(most * 2 > stats.steps);
+}
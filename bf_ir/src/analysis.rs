@@ -0,0 +1,122 @@
+//! Static analysis of loops: finding those that can never terminate
+//! once entered, and removing those that can never be entered.
+
+use {Op, Pos};
+
+pub struct Report {
+    /// The number of loops in the program, including dead ones (but
+    /// not those nested inside dead ones).
+    pub loops: usize,
+    /// Loops that can never terminate once entered.
+    pub infinite: Vec<Pos>,
+    /// Loops that can never be entered (and so can be removed).
+    pub dead: Vec<Pos>,
+}
+
+/// What is known about the tape at some point in the program.
+#[derive(Clone, Copy)]
+struct State {
+    /// The current cell is definitely zero.
+    cell_zero: bool,
+    /// Every cell is definitely zero.
+    tape_zero: bool,
+}
+
+/// Analyse `ops`, returning them with the dead loops removed if
+/// `remove_dead` is set.
+///
+/// If the program is `threaded`, other threads can change any cell
+/// at any time, so much less is known.
+pub fn analyse(ops: Vec<Op>, threaded: bool, remove_dead: bool) -> (Vec<Op>, Report) {
+    let mut report = Report { loops: 0, infinite: vec![], dead: vec![] };
+    let state = State { cell_zero: true, tape_zero: true };
+    let ops = report.block(ops, state, threaded, remove_dead);
+    (ops, report)
+}
+
+impl Report {
+    fn block(&mut self, ops: Vec<Op>, mut state: State, threaded: bool,
+             remove_dead: bool) -> Vec<Op> {
+        let mut out = Vec::with_capacity(ops.len());
+        for op in ops.into_iter() {
+            match op {
+                Op::Loop(pos, body) => {
+                    self.loops += 1;
+                    if state.cell_zero {
+                        self.dead.push(pos);
+                        if !remove_dead {
+                            out.push(Op::Loop(pos, body))
+                        }
+                        continue
+                    }
+                    if !threaded && never_terminates(&*body) {
+                        self.infinite.push(pos)
+                    }
+                    // the loop is only entered when the cell is
+                    // non-zero, and only left when it is zero.
+                    let body = self.block(body, State { cell_zero: false, tape_zero: false },
+                                          threaded, remove_dead);
+                    out.push(Op::Loop(pos, body));
+                    state = State { cell_zero: !threaded, tape_zero: false };
+                }
+                Op::Proc(pos, body) => {
+                    // a procedure can be called from anywhere, so
+                    // nothing is known when it starts.
+                    let body = self.block(body, State { cell_zero: false, tape_zero: false },
+                                          threaded, remove_dead);
+                    out.push(Op::Proc(pos, body))
+                }
+                Op::Add(_) | Op::In | Op::Call | Op::Fork => {
+                    state = State { cell_zero: false, tape_zero: false };
+                    out.push(op)
+                }
+                Op::Move(_) => {
+                    state.cell_zero = state.tape_zero;
+                    out.push(op)
+                }
+                Op::Load | Op::Not | Op::Flip => {
+                    state = State { cell_zero: false, tape_zero: false };
+                    out.push(op)
+                }
+                Op::Clear => {
+                    state.cell_zero = true;
+                    out.push(op)
+                }
+                // like a loop, these finish on a zero cell
+                Op::Scan(_) | Op::Mul(..) => {
+                    state = State { cell_zero: !threaded, tape_zero: false };
+                    out.push(op)
+                }
+                // shifting zero gives zero
+                Op::Out | Op::Debug(_) | Op::Store | Op::ShiftRight | Op::ShiftLeft | Op::End => {
+                    out.push(op)
+                }
+            }
+        }
+        out
+    }
+}
+
+/// A loop body that doesn't move, doesn't read and doesn't change the
+/// current cell overall can never terminate, since the cell has the
+/// same non-zero value each time around.
+///
+/// Moves aren't tracked at all, since the pointer is clamped at the
+/// ends of the tape, so `<>` isn't necessarily a no-op. Adds only
+/// cancel out if they total exactly zero: the cells might be wider
+/// than 8 bits in the back end, so `+` 256 times can change them.
+fn never_terminates(body: &[Op]) -> bool {
+    let mut total = 0i64;
+    let mut flipped = false;
+    for op in body.iter() {
+        match *op {
+            Op::Add(d) => total += d as i64,
+            Op::Flip => flipped = !flipped,
+            Op::Out | Op::Proc(..) | Op::Debug(_) | Op::Store => {}
+            Op::Move(_) | Op::In | Op::Loop(..) | Op::Call | Op::Fork | Op::End |
+            Op::Load | Op::ShiftRight | Op::ShiftLeft | Op::Not |
+            Op::Clear | Op::Scan(_) | Op::Mul(..) => return false
+        }
+    }
+    total == 0 && !flipped
+}
@@ -0,0 +1,275 @@
+//! The commands of a brainfuck program as a tree, shared between the
+//! `brainfuck!` front end, its code generator, and the runtime back
+//! ends, so that they can all be analysed and optimised in the same
+//! way.
+
+#![crate_name="bf_ir"]
+#![crate_type="lib"]
+
+use std::fmt;
+
+pub mod analysis;
+pub mod opt;
+
+/// Where something came from in the source, as byte offsets. For
+/// `brainfuck!` these are positions in the codemap, and for
+/// [`parse`](fn.parse.html) they're offsets into the program.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub struct Pos {
+    pub lo: usize,
+    pub hi: usize,
+}
+
+#[derive(Clone, PartialEq, Debug)]
+pub enum Op {
+    /// Add to the current cell, wrapping: `+` is 1 and `-` is -1.
+    /// The optimisation passes never wrap the amount themselves, so
+    /// it's correct for any cell width, and the back end has to wrap
+    /// it (like `+= n as u8` for bytes).
+    Add(i32),
+    /// Move the pointer, clamped at the ends of the tape: `>` is 1
+    /// and `<` is -1.
+    Move(isize),
+    /// `,`
+    In,
+    /// `.`
+    Out,
+    /// `[...]`, along with the position of the whole loop.
+    Loop(Pos, Vec<Op>),
+    /// pbrain's `(...)`, defining a procedure numbered by the current
+    /// cell, along with the position of the whole definition.
+    Proc(Pos, Vec<Op>),
+    /// pbrain's `:`, calling the procedure numbered by the current
+    /// cell.
+    Call,
+    /// Brainfork's `Y`, forking a new thread.
+    Fork,
+    /// Extended Brainfuck's `@`, ending the program.
+    End,
+    /// Extended Brainfuck's `$`, storing the cell in the register.
+    Store,
+    /// Extended Brainfuck's `!`, loading the register into the cell.
+    Load,
+    /// Extended Brainfuck's `}`, shifting the cell right one bit.
+    ShiftRight,
+    /// Extended Brainfuck's `{`, shifting the cell left one bit.
+    ShiftLeft,
+    /// Extended Brainfuck's `~`, the bitwise NOT of the cell.
+    Not,
+    /// Boolfuck's `+` and Smallfuck's `*`, flipping the current bit.
+    Flip,
+    /// `#`, dumping the tape around the pointer, along with its
+    /// position, for the location.
+    Debug(Pos),
+
+    // Ops that only come from optimisation passes.
+
+    /// Set the current cell to zero, like `[-]`.
+    Clear,
+    /// Move by the given step until the current cell is zero, like
+    /// `[>]` or `[<<]`.
+    Scan(isize),
+    /// A loop like `[->+>++<<]` that just adds multiples of the
+    /// current cell to others and clears it: the offset and factor
+    /// of each other cell, along with the original loop, to run
+    /// instead when any of the cells it visits are off the ends of
+    /// the tape (so that the clamping is the same).
+    Mul(Pos, Vec<(isize, i32)>, Vec<Op>),
+}
+
+/// The dialects of brainfuck, the same as `brainfuck!`'s `dialect`
+/// option.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum Dialect {
+    Brainfuck,
+    /// Procedures: `(...)` defines one, `:` calls one.
+    Pbrain,
+    /// Threads: `Y` forks.
+    Brainfork,
+    /// Extended Brainfuck Type I: `@` ends, `$` and `!` store and
+    /// load a register, `}` and `{` shift, and `~` negates.
+    Ebf1,
+    /// Single-bit cells: `+` flips, and `,` and `;` read and write
+    /// a bit, least significant bit of each byte first.
+    Boolfuck,
+    /// Single-bit cells: `*` flips, and there's no I/O.
+    Smallfuck,
+}
+
+/// Every dialect.
+pub static DIALECTS: &'static [Dialect] = &[
+    Dialect::Brainfuck,
+    Dialect::Pbrain,
+    Dialect::Brainfork,
+    Dialect::Ebf1,
+    Dialect::Boolfuck,
+    Dialect::Smallfuck,
+];
+
+impl Dialect {
+    pub fn name(self) -> &'static str {
+        match self {
+            Dialect::Brainfuck => "brainfuck",
+            Dialect::Pbrain => "pbrain",
+            Dialect::Brainfork => "brainfork",
+            Dialect::Ebf1 => "ebf1",
+            Dialect::Boolfuck => "boolfuck",
+            Dialect::Smallfuck => "smallfuck",
+        }
+    }
+
+    pub fn from_name(name: &str) -> Option<Dialect> {
+        DIALECTS.iter().cloned().find(|d| d.name() == name)
+    }
+
+    /// Whether `!` separates the program from its input.
+    pub fn input_separator(self) -> bool {
+        self != Dialect::Ebf1
+    }
+
+    /// Whether cells are single bits, packed eight to a byte of the
+    /// tape.
+    pub fn bits(self) -> bool {
+        self == Dialect::Boolfuck || self == Dialect::Smallfuck
+    }
+
+    /// The op for the command `c`, other than those that open and
+    /// close loops and procedures.
+    pub fn command(self, c: char) -> Option<Op> {
+        match self {
+            Dialect::Boolfuck => return match c {
+                '<' => Some(Op::Move(-1)),
+                '>' => Some(Op::Move(1)),
+                '+' => Some(Op::Flip),
+                ',' => Some(Op::In),
+                ';' => Some(Op::Out),
+                _ => None
+            },
+            Dialect::Smallfuck => return match c {
+                '<' => Some(Op::Move(-1)),
+                '>' => Some(Op::Move(1)),
+                '*' => Some(Op::Flip),
+                _ => None
+            },
+            _ => {}
+        }
+        match c {
+            ':' if self == Dialect::Pbrain => Some(Op::Call),
+            'Y' if self == Dialect::Brainfork => Some(Op::Fork),
+            '@' if self == Dialect::Ebf1 => Some(Op::End),
+            '$' if self == Dialect::Ebf1 => Some(Op::Store),
+            '!' if self == Dialect::Ebf1 => Some(Op::Load),
+            '}' if self == Dialect::Ebf1 => Some(Op::ShiftRight),
+            '{' if self == Dialect::Ebf1 => Some(Op::ShiftLeft),
+            '~' if self == Dialect::Ebf1 => Some(Op::Not),
+            '<' => Some(Op::Move(-1)),
+            '>' => Some(Op::Move(1)),
+            '+' => Some(Op::Add(1)),
+            '-' => Some(Op::Add(-1)),
+            '.' => Some(Op::Out),
+            ',' => Some(Op::In),
+            _ => None
+        }
+    }
+
+    /// The characters that open and close a block: `[` and `]` for
+    /// loops, and in pbrain, `(` and `)` for procedures.
+    fn is_open(self, c: char) -> bool {
+        c == '[' || (c == '(' && self == Dialect::Pbrain)
+    }
+
+    fn is_close(self, c: char) -> bool {
+        c == ']' || (c == ')' && self == Dialect::Pbrain)
+    }
+
+    /// Whether `c` is a command in the dialect, including those for
+    /// loops and procedures.
+    pub fn is_command(self, c: char) -> bool {
+        self.is_open(c) || self.is_close(c) || self.command(c).is_some()
+    }
+}
+
+impl Default for Dialect {
+    fn default() -> Dialect { Dialect::Brainfuck }
+}
+
+/// A program whose loops (or procedures) couldn't be matched up, or
+/// that a back end can't handle.
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum Error {
+    /// A `]` (or `)`) without a `[` (or `(`), at this byte offset.
+    Unmatched(usize),
+    /// A `[` (or `(`) without a `]` (or `)`), at this byte offset.
+    Unclosed(usize),
+    /// The program has ops that the back end doesn't support, like
+    /// those of the other dialects.
+    Unsupported,
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            Error::Unmatched(pos) => write!(f, "unmatched closing bracket at byte {}", pos),
+            Error::Unclosed(pos) => write!(f, "unclosed bracket at byte {}", pos),
+            Error::Unsupported => write!(f, "the program uses commands that this back end doesn't support"),
+        }
+    }
+}
+
+/// A parsed program, and the input embedded in it.
+#[derive(Clone, PartialEq, Debug)]
+pub struct Program {
+    pub ops: Vec<Op>,
+    /// The input after the first `!`, to be read before anything
+    /// from the real input.
+    pub input: Vec<u8>,
+}
+
+/// Parse `src` as plain brainfuck, where every byte other than the
+/// eight commands is a comment, and everything after the first `!`
+/// is input (just like a string literal given to `brainfuck!`).
+pub fn parse(src: &[u8]) -> Result<Program, Error> {
+    parse_dialect(src, Dialect::Brainfuck)
+}
+
+/// Parse `src` as a program in `dialect`, like `parse`. In Extended
+/// Brainfuck, `!` is a command, so there's no embedded input.
+pub fn parse_dialect(src: &[u8], dialect: Dialect) -> Result<Program, Error> {
+    let (code, input) = match src.iter().position(|&b| b == b'!') {
+        Some(i) if dialect.input_separator() => (&src[..i], src[i + 1..].to_vec()),
+        _ => (src, vec![])
+    };
+
+    // the bodies of each enclosing loop or procedure, along with the
+    // offset of its `[` or `(`.
+    let mut stack = vec![];
+    let mut current = vec![];
+    for (pos, &b) in code.iter().enumerate() {
+        let c = b as char;
+        if dialect.is_open(c) {
+            stack.push((pos, c, current));
+            current = vec![];
+            continue
+        }
+        let op = if dialect.is_close(c) {
+            match stack.pop() {
+                Some((lo, open, outer)) if (open == '[') == (c == ']') => {
+                    let body = std::mem::replace(&mut current, outer);
+                    let pos = Pos { lo: lo, hi: pos + 1 };
+                    if c == ']' { Op::Loop(pos, body) } else { Op::Proc(pos, body) }
+                }
+                _ => return Err(Error::Unmatched(pos))
+            }
+        } else {
+            match dialect.command(c) {
+                Some(op) => op,
+                None => continue
+            }
+        };
+        current.push(op)
+    }
+    if let Some(&(pos, _, _)) = stack.first() {
+        return Err(Error::Unclosed(pos))
+    }
+    Ok(Program { ops: current, input: input })
+}
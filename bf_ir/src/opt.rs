@@ -0,0 +1,188 @@
+//! Optimisation passes over the IR. None of them change what a
+//! program does, including at the ends of the tape.
+
+use {Op, Pos};
+use analysis;
+
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum Pass {
+    /// Remove loops that can never be entered.
+    DeadLoops,
+    /// Merge runs of `+` and `-`, and of moves in the same direction.
+    Combine,
+    /// Turn `[-]` (and any other loop that just adds an odd number)
+    /// into `Clear`.
+    Clear,
+    /// Turn `[>]` (and any other loop that just moves) into `Scan`.
+    Scan,
+    /// Turn multiplication loops like `[->+>++<<]` into `Mul`.
+    Mul,
+}
+
+/// Every pass, in the order they run.
+pub static PASSES: &'static [Pass] = &[
+    Pass::DeadLoops,
+    Pass::Combine,
+    Pass::Clear,
+    Pass::Scan,
+    Pass::Mul,
+];
+
+/// The highest optimisation level.
+pub const MAX_LEVEL: u32 = 3;
+
+impl Pass {
+    pub fn name(self) -> &'static str {
+        match self {
+            Pass::DeadLoops => "dead_loops",
+            Pass::Combine => "combine",
+            Pass::Clear => "clear",
+            Pass::Scan => "scan",
+            Pass::Mul => "mul",
+        }
+    }
+
+    pub fn from_name(name: &str) -> Option<Pass> {
+        PASSES.iter().cloned().find(|p| p.name() == name)
+    }
+
+    /// The lowest optimisation level that runs the pass.
+    pub fn level(self) -> u32 {
+        match self {
+            Pass::DeadLoops | Pass::Combine => 1,
+            Pass::Clear | Pass::Scan => 2,
+            Pass::Mul => 3,
+        }
+    }
+}
+
+/// The passes run at optimisation `level`, from 0 (none at all) to
+/// `MAX_LEVEL`.
+pub fn passes(level: u32) -> Vec<Pass> {
+    PASSES.iter().cloned().filter(|p| p.level() <= level).collect()
+}
+
+/// Run `passes` over `ops`.
+///
+/// Only `DeadLoops` is run on `threaded` programs, since the others
+/// change how many steps a thread takes, and so how the threads
+/// interleave.
+pub fn optimise(mut ops: Vec<Op>, passes: &[Pass], threaded: bool) -> Vec<Op> {
+    for &pass in PASSES.iter().filter(|&p| passes.contains(p)) {
+        if threaded && pass != Pass::DeadLoops { continue }
+        ops = match pass {
+            Pass::DeadLoops => analysis::analyse(ops, threaded, true).0,
+            Pass::Combine => combine(ops),
+            pass => loops(ops, pass),
+        }
+    }
+    ops
+}
+
+fn combine(ops: Vec<Op>) -> Vec<Op> {
+    let mut out: Vec<Op> = Vec::with_capacity(ops.len());
+    for op in ops.into_iter() {
+        let op = match (out.pop(), op) {
+            // not wrapped to a byte, since the back end might not
+            // have byte cells
+            (Some(Op::Add(a)), Op::Add(b)) => Op::Add(a + b),
+            // moves in opposite directions can't be merged, since
+            // `<>` isn't a no-op at the start of the tape
+            (Some(Op::Move(a)), Op::Move(b)) if (a < 0) == (b < 0) => Op::Move(a + b),
+            (last, op) => {
+                out.extend(last.into_iter());
+                match op {
+                    Op::Loop(pos, body) => Op::Loop(pos, combine(body)),
+                    Op::Proc(pos, body) => Op::Proc(pos, combine(body)),
+                    op => op
+                }
+            }
+        };
+        match op {
+            Op::Add(0) => {}
+            op => out.push(op)
+        }
+    }
+    out
+}
+
+/// Run one of the passes that replace whole loops, innermost first.
+fn loops(ops: Vec<Op>, pass: Pass) -> Vec<Op> {
+    ops.into_iter().map(|op| {
+        match op {
+            Op::Loop(pos, body) => {
+                let body = loops(body, pass);
+                let replacement = match pass {
+                    Pass::Clear => clear(&*body),
+                    Pass::Scan => scan(&*body),
+                    Pass::Mul => mul(pos, &*body),
+                    _ => None
+                };
+                replacement.unwrap_or(Op::Loop(pos, body))
+            }
+            Op::Proc(pos, body) => Op::Proc(pos, loops(body, pass)),
+            op => op
+        }
+    }).collect()
+}
+
+fn clear(body: &[Op]) -> Option<Op> {
+    match body.first() {
+        // an odd number is coprime to 256, so the cell always gets to
+        // zero
+        Some(&Op::Add(n)) if body.len() == 1 && n % 2 != 0 => Some(Op::Clear),
+        _ => None
+    }
+}
+
+fn scan(body: &[Op]) -> Option<Op> {
+    match body.first() {
+        Some(&Op::Move(n)) if body.len() == 1 => Some(Op::Scan(n)),
+        _ => None
+    }
+}
+
+fn mul(pos: Pos, body: &[Op]) -> Option<Op> {
+    let mut offset = 0;
+    // the total added to each cell, in the order they're first
+    // changed
+    let mut deltas: Vec<(isize, i32)> = vec![];
+    for op in body.iter() {
+        match *op {
+            Op::Add(n) => {
+                match deltas.iter().position(|&(off, _)| off == offset) {
+                    Some(i) => deltas[i].1 += n,
+                    None => deltas.push((offset, n))
+                }
+            }
+            Op::Move(n) => offset += n,
+            _ => return None
+        }
+    }
+    // the loop has to end where it started, having subtracted one
+    // from the current cell (exactly one, since something like 255
+    // is only -1 with byte cells)
+    let own = deltas.iter().find(|&&(off, _)| off == 0).map(|&(_, n)| n);
+    if offset != 0 || own != Some(-1) {
+        return None
+    }
+
+    let targets = deltas.into_iter()
+        .filter(|&(off, n)| off != 0 && n != 0)
+        .collect();
+    Some(Op::Mul(pos, targets, body.to_vec()))
+}
+
+/// The lowest and highest offsets from the starting cell that `body`
+/// visits, for the `Mul` bounds check.
+pub fn visited(body: &[Op]) -> (isize, isize) {
+    let (mut offset, mut lo, mut hi) = (0, 0, 0);
+    for op in body.iter() {
+        if let Op::Move(n) = *op {
+            offset += n;
+            if offset < lo { lo = offset }
+            if offset > hi { hi = offset }
+        }
+    }
+    (lo, hi)
+}
@@ -0,0 +1,70 @@
+extern crate bf_ir;
+
+use bf_ir::{parse, Op};
+use bf_ir::analysis;
+use bf_ir::opt::{self, Pass};
+
+fn optimise(src: &[u8], passes: &[Pass]) -> Vec<Op> {
+    opt::optimise(parse(src).unwrap().ops, passes, false)
+}
+
+#[test]
+fn levels() {
+    assert!(opt::passes(0).is_empty());
+    assert_eq!(opt::passes(1), [Pass::DeadLoops, Pass::Combine]);
+    assert_eq!(opt::passes(3).len(), opt::PASSES.len());
+    assert_eq!(Pass::from_name("mul"), Some(Pass::Mul));
+}
+
+#[test]
+fn combine() {
+    assert_eq!(optimise(b"+++--<<>>>", &[Pass::Combine]),
+               [Op::Add(1), Op::Move(-2), Op::Move(3)]);
+    assert_eq!(optimise(b"+-.", &[Pass::Combine]), [Op::Out]);
+    // left for the back end to wrap to its cell width
+    let src: Vec<u8> = (0..257).map(|_| b'+').collect();
+    assert_eq!(optimise(&*src, &[Pass::Combine]), [Op::Add(257)]);
+}
+
+#[test]
+fn dead_loops() {
+    assert_eq!(optimise(b"[-]+[-]", &[Pass::DeadLoops]).len(), 2);
+}
+
+#[test]
+fn infinite_loops() {
+    let infinite = |src: &[u8]| {
+        analysis::analyse(parse(src).unwrap().ops, false, false).1.infinite.len()
+    };
+    assert_eq!(infinite(b"+[-+]"), 1);
+    assert_eq!(infinite(b"+[---]"), 0);
+    // adding 256 only wraps back around with 8 bit cells
+    let mut src = b"+[".to_vec();
+    src.extend((0..256).map(|_| b'+'));
+    src.push(b']');
+    assert_eq!(infinite(&*src), 0);
+    assert_eq!(optimise(b"[-+]+[-+]", &*opt::passes(3)).len(), 2);
+}
+
+#[test]
+fn loops() {
+    let passes = opt::passes(3);
+    assert_eq!(optimise(b"+[---]", &*passes), [Op::Add(1), Op::Clear]);
+    assert_eq!(optimise(b"+[<<]", &*passes), [Op::Add(1), Op::Scan(-2)]);
+    match optimise(b"+[->++<<+++>]", &*passes)[1] {
+        Op::Mul(_, ref targets, _) => assert_eq!(*targets, [(1, 2), (-1, 3)]),
+        ref op => panic!("not a multiplication: {:?}", op)
+    }
+    // doesn't end where it started
+    assert!(match optimise(b"+[->+]", &*passes)[1] { Op::Loop(..) => true, _ => false });
+    // adding 255 is only subtracting one with byte cells
+    let mut src = b"+[>+<".to_vec();
+    src.extend((0..255).map(|_| b'+'));
+    src.push(b']');
+    assert!(match optimise(&*src, &*passes)[1] { Op::Loop(..) => true, _ => false });
+}
+
+#[test]
+fn visited() {
+    assert_eq!(opt::visited(&*parse(b"->>+<<<+>").unwrap().ops), (-1, 2));
+}
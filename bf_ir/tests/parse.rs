@@ -0,0 +1,54 @@
+extern crate bf_ir;
+
+use bf_ir::{parse, Op, Pos, Program, Error};
+
+#[test]
+fn commands() {
+    assert_eq!(parse(b"+-<>,. comment"),
+               Ok(Program {
+                   ops: vec![Op::Add(1), Op::Add(-1), Op::Move(-1), Op::Move(1), Op::In, Op::Out],
+                   input: vec![],
+               }));
+}
+
+#[test]
+fn loops() {
+    assert_eq!(parse(b"+[-[>]]").unwrap().ops,
+               vec![Op::Add(1),
+                    Op::Loop(Pos { lo: 1, hi: 7 },
+                             vec![Op::Add(-1),
+                                  Op::Loop(Pos { lo: 3, hi: 6 }, vec![Op::Move(1)])])]);
+}
+
+#[test]
+fn input() {
+    assert_eq!(parse(b",.!]ab"),
+               Ok(Program { ops: vec![Op::In, Op::Out], input: b"]ab".to_vec() }));
+}
+
+#[test]
+fn errors() {
+    assert_eq!(parse(b"+[[]"), Err(Error::Unclosed(1)));
+    assert_eq!(parse(b"+]"), Err(Error::Unmatched(1)));
+}
+
+#[test]
+fn dialects() {
+    use bf_ir::{parse_dialect, Dialect};
+
+    assert_eq!(parse_dialect(b"+(.):", Dialect::Pbrain).unwrap().ops,
+               vec![Op::Add(1), Op::Proc(Pos { lo: 1, hi: 4 }, vec![Op::Out]), Op::Call]);
+    // only pbrain has procedures
+    assert_eq!(parse_dialect(b"(:)", Dialect::Brainfuck).unwrap().ops, vec![]);
+    assert_eq!(parse_dialect(b"([)]", Dialect::Pbrain), Err(Error::Unmatched(2)));
+    assert_eq!(parse_dialect(b"+Y", Dialect::Brainfork).unwrap().ops, vec![Op::Add(1), Op::Fork]);
+    // `!` is a command in Extended Brainfuck, not the start of the input
+    assert_eq!(parse_dialect(b"$>!@", Dialect::Ebf1),
+               Ok(Program { ops: vec![Op::Store, Op::Move(1), Op::Load, Op::End], input: vec![] }));
+    assert_eq!(parse_dialect(b"+-;!a", Dialect::Boolfuck),
+               Ok(Program { ops: vec![Op::Flip, Op::Out], input: b"a".to_vec() }));
+    assert_eq!(parse_dialect(b"*+<", Dialect::Smallfuck).unwrap().ops,
+               vec![Op::Flip, Op::Move(-1)]);
+    assert_eq!(Dialect::from_name("ebf1"), Some(Dialect::Ebf1));
+    assert_eq!(Dialect::from_name("ook"), None);
+}
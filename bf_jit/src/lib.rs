@@ -0,0 +1,76 @@
+//! A JIT compiler for brainfuck programs that are only known at
+//! runtime. On x86-64 Unix, programs are compiled straight to
+//! machine code in memory; everywhere else they're run by
+//! `bf_interpreter`. Either way, the semantics are the same as
+//! `brainfuck!`.
+
+#![crate_name="bf_jit"]
+#![crate_type="lib"]
+#![feature(io, os, core)]
+
+extern crate bf_ir;
+extern crate bf_interpreter;
+
+use std::old_io as io;
+
+use bf_ir::opt;
+
+pub use bf_ir::Error;
+
+// the native code generator for this target, if there is one
+#[cfg(all(target_arch = "x86_64", unix))]
+#[path = "x86_64.rs"]
+mod native;
+#[cfg(not(all(target_arch = "x86_64", unix)))]
+#[path = "unsupported.rs"]
+mod native;
+
+enum Imp {
+    /// The code, along with the input embedded after the first `!`.
+    Native(native::Code, Vec<u8>),
+    Interpreted(bf_interpreter::Program),
+}
+
+pub struct Program {
+    imp: Imp,
+}
+
+impl Program {
+    /// Parse and compile `src`, in the same format as
+    /// `bf_interpreter::Program::parse`.
+    pub fn parse(src: &[u8]) -> Result<Program, Error> {
+        let mut program = try!(bf_ir::parse(src));
+        program.ops = opt::optimise(program.ops, &*opt::passes(opt::MAX_LEVEL), false);
+        Ok(Program::from_ir(program))
+    }
+
+    /// Compile a program. Only the plain brainfuck ops and those from
+    /// `bf_ir::opt` are compiled to native code (any `Debug`s are
+    /// ignored); programs with any others, like those of the other
+    /// dialects, are run by the interpreter instead.
+    pub fn from_ir(program: bf_ir::Program) -> Program {
+        let imp = match native::compile(&*program.ops) {
+            Some(code) => Imp::Native(code, program.input),
+            None => Imp::Interpreted(bf_interpreter::Program::from_ir(program))
+        };
+        Program { imp: imp }
+    }
+
+    /// Whether the program was compiled to native code, rather than
+    /// falling back to the interpreter.
+    pub fn is_native(&self) -> bool {
+        match self.imp {
+            Imp::Native(..) => true,
+            Imp::Interpreted(_) => false,
+        }
+    }
+
+    /// Run the program, returning the final tape, just like the
+    /// functions generated by `brainfuck!`.
+    pub fn run(&self, r: &mut Reader, w: &mut Writer) -> io::IoResult<Vec<u8>> {
+        match self.imp {
+            Imp::Native(ref code, ref input) => code.run(&**input, r, w),
+            Imp::Interpreted(ref program) => program.run(r, w),
+        }
+    }
+}
@@ -0,0 +1,18 @@
+//! There's no native code generator for this target, so everything
+//! falls back to the interpreter.
+
+use std::old_io as io;
+
+use bf_ir::Op;
+
+pub enum Code {}
+
+pub fn compile(_: &[Op]) -> Option<Code> {
+    None
+}
+
+impl Code {
+    pub fn run(&self, _: &[u8], _: &mut Reader, _: &mut Writer) -> io::IoResult<Vec<u8>> {
+        match *self {}
+    }
+}
@@ -0,0 +1,264 @@
+//! Compiling to x86-64 machine code, using the System V calling
+//! convention.
+//!
+//! The generated function is `extern "C" fn(tape: *mut u8, ctx: *mut
+//! Context) -> u32`, returning 0 on success and 1 if reading or
+//! writing failed (with the error stored in the context). While it
+//! runs, `rbx` holds the start of the tape, `r12` the index of the
+//! current cell, `r13` the context and `r14` the length of the tape.
+
+use std::mem;
+use std::old_io as io;
+use std::os::{MemoryMap, MapOption};
+
+use bf_ir::Op;
+use bf_interpreter::TAPE_LEN;
+
+/// The compiled code, mapped into executable memory.
+pub struct Code {
+    map: MemoryMap,
+}
+
+/// The state that the I/O callbacks need.
+struct Context<'a> {
+    r: &'a mut (Reader + 'a),
+    w: &'a mut (Writer + 'a),
+    /// The rest of the embedded input.
+    input: &'a [u8],
+    error: Option<io::IoError>,
+}
+
+/// Read a byte, returning -1 on the end of the input (just like
+/// `brainfuck!`), or something outside `u8` on an error.
+extern "C" fn read(ctx: *mut Context) -> i32 {
+    let ctx = unsafe { &mut *ctx };
+    if !ctx.input.is_empty() {
+        let b = ctx.input[0];
+        ctx.input = &ctx.input[1..];
+        return b as i32
+    }
+    match ctx.r.read_byte() {
+        Ok(b) => b as i32,
+        Err(io::IoError { kind: io::EndOfFile, .. }) => 255,
+        Err(e) => {
+            ctx.error = Some(e);
+            -1
+        }
+    }
+}
+
+/// Write a byte, returning non-zero on an error.
+extern "C" fn write(ctx: *mut Context, b: u8) -> i32 {
+    let ctx = unsafe { &mut *ctx };
+    match ctx.w.write(&[b]) {
+        Ok(()) => 0,
+        Err(e) => {
+            ctx.error = Some(e);
+            1
+        }
+    }
+}
+
+/// Compile `ops`, or return `None` if they can't be compiled (like
+/// those from the other dialects), to fall back to the interpreter.
+pub fn compile(ops: &[Op]) -> Option<Code> {
+    let mut asm = Assembler { code: vec![], errors: vec![] };
+    asm.prologue();
+    if !asm.ops(ops) {
+        return None
+    }
+    asm.epilogue();
+
+    let map = match MemoryMap::new(asm.code.len(), &[MapOption::MapReadable,
+                                                       MapOption::MapWritable,
+                                                       MapOption::MapExecutable]) {
+        Ok(map) => map,
+        // no executable memory, so fall back to the interpreter
+        Err(_) => return None
+    };
+    for (i, &b) in asm.code.iter().enumerate() {
+        unsafe { *map.data().offset(i as isize) = b }
+    }
+    Some(Code { map: map })
+}
+
+impl Code {
+    pub fn run(&self, input: &[u8], r: &mut Reader, w: &mut Writer) -> io::IoResult<Vec<u8>> {
+        let mut tape = vec![0u8; TAPE_LEN];
+        let mut ctx = Context { r: r, w: w, input: input, error: None };
+        let result = unsafe {
+            let f: extern "C" fn(*mut u8, *mut Context) -> u32 = mem::transmute(self.map.data());
+            f(tape.as_mut_ptr(), &mut ctx)
+        };
+        match (result, ctx.error) {
+            (0, _) => Ok(tape),
+            (_, Some(e)) => Err(e),
+            (_, None) => Err(io::IoError {
+                kind: io::OtherIoError,
+                desc: "bf_jit: the program failed without an error",
+                detail: None
+            })
+        }
+    }
+}
+
+struct Assembler {
+    code: Vec<u8>,
+    /// The positions of the jumps to the error exit.
+    errors: Vec<usize>,
+}
+
+// `[rbx + r12]`, after an opcode with a REX.X prefix, and with the
+// register (or opcode extension) in bits 3-5 of the ModRM byte.
+const CELL_MODRM: u8 = 0b00_000_100;
+const CELL_SIB: u8 = 0b00_100_011;
+const REX_X: u8 = 0x42;
+
+impl Assembler {
+    fn emit(&mut self, bytes: &[u8]) {
+        self.code.extend(bytes.iter().cloned())
+    }
+
+    fn imm32(&mut self, n: u32) {
+        for i in 0..4 {
+            self.code.push((n >> (8 * i)) as u8)
+        }
+    }
+
+    fn imm64(&mut self, n: u64) {
+        for i in 0..8 {
+            self.code.push((n >> (8 * i)) as u8)
+        }
+    }
+
+    /// Emit a jump with a 32-bit displacement, to be filled in later
+    /// with `patch`, returning its position.
+    fn jump(&mut self, opcode: &[u8]) -> usize {
+        self.emit(opcode);
+        let at = self.code.len();
+        self.imm32(0);
+        at
+    }
+
+    /// Point the jump at `at` to `target`.
+    fn patch(&mut self, at: usize, target: usize) {
+        let rel = (target as isize - (at as isize + 4)) as i32 as u32;
+        for i in 0..4 {
+            self.code[at + i] = (rel >> (8 * i)) as u8
+        }
+    }
+
+    fn prologue(&mut self) {
+        // push rbx; push r12; push r13; push r14; push r15 (which also
+        // leaves the stack 16-byte aligned for calls)
+        self.emit(&[0x53, 0x41, 0x54, 0x41, 0x55, 0x41, 0x56, 0x41, 0x57]);
+        // mov rbx, rdi; mov r13, rsi
+        self.emit(&[0x48, 0x89, 0xfb, 0x49, 0x89, 0xf5]);
+        // xor r12d, r12d
+        self.emit(&[0x45, 0x31, 0xe4]);
+        // mov r14d, TAPE_LEN
+        self.emit(&[0x41, 0xbe]);
+        self.imm32(TAPE_LEN as u32);
+    }
+
+    fn epilogue(&mut self) {
+        // xor eax, eax
+        self.emit(&[0x31, 0xc0]);
+        // jmp over the error exit, to the return
+        self.emit(&[0xeb, 0x05]);
+        let error = self.code.len();
+        // mov eax, 1
+        self.emit(&[0xb8]);
+        self.imm32(1);
+        // pop r15; pop r14; pop r13; pop r12; pop rbx; ret
+        self.emit(&[0x41, 0x5f, 0x41, 0x5e, 0x41, 0x5d, 0x41, 0x5c, 0x5b, 0xc3]);
+
+        for at in mem::replace(&mut self.errors, vec![]).into_iter() {
+            self.patch(at, error)
+        }
+    }
+
+    /// Call `f` with the context as the first argument, with `rax`
+    /// clobbered by the address.
+    fn call(&mut self, f: usize) {
+        // mov rdi, r13
+        self.emit(&[0x4c, 0x89, 0xef]);
+        // mov rax, f; call rax
+        self.emit(&[0x48, 0xb8]);
+        self.imm64(f as u64);
+        self.emit(&[0xff, 0xd0]);
+    }
+
+    /// Emit the code for `ops`, returning false if any of them aren't
+    /// supported.
+    fn ops(&mut self, ops: &[Op]) -> bool {
+        ops.iter().all(|op| self.op(op))
+    }
+
+    fn op(&mut self, op: &Op) -> bool {
+        match *op {
+            Op::Add(n) => {
+                // add byte [rbx + r12], n
+                self.emit(&[REX_X, 0x80, CELL_MODRM, CELL_SIB, n as u8]);
+            }
+            Op::Move(n) if n < 0 => {
+                // sub r12, -n; jae ok; xor r12d, r12d; ok:
+                self.emit(&[0x49, 0x81, 0xec]);
+                self.imm32(-n as u32);
+                self.emit(&[0x73, 0x03, 0x45, 0x31, 0xe4]);
+            }
+            Op::Move(n) => {
+                // add r12, n; cmp r12, r14; jb ok; lea r12, [r14 - 1]; ok:
+                self.emit(&[0x49, 0x81, 0xc4]);
+                self.imm32(n as u32);
+                self.emit(&[0x4d, 0x39, 0xf4, 0x72, 0x04, 0x4d, 0x8d, 0x66, 0xff]);
+            }
+            Op::In => {
+                self.call(read as usize);
+                // test eax, eax; js error
+                self.emit(&[0x85, 0xc0]);
+                let at = self.jump(&[0x0f, 0x88]);
+                self.errors.push(at);
+                // mov [rbx + r12], al
+                self.emit(&[REX_X, 0x88, CELL_MODRM, CELL_SIB]);
+            }
+            Op::Out => {
+                // movzx esi, byte [rbx + r12]
+                self.emit(&[REX_X, 0x0f, 0xb6, CELL_MODRM | 0b110_000, CELL_SIB]);
+                self.call(write as usize);
+                // test eax, eax; jnz error
+                self.emit(&[0x85, 0xc0]);
+                let at = self.jump(&[0x0f, 0x85]);
+                self.errors.push(at);
+            }
+            Op::Clear => {
+                // mov byte [rbx + r12], 0
+                self.emit(&[REX_X, 0xc6, CELL_MODRM, CELL_SIB, 0]);
+            }
+            // multiplication loops (including their bounds checks) and
+            // scans are left to the processor to run quickly
+            Op::Loop(_, ref body) | Op::Mul(_, _, ref body) => return self.loop_(&**body),
+            Op::Scan(n) => return self.loop_(&[Op::Move(n)]),
+            Op::Debug(_) => {}
+            _ => return false
+        }
+        true
+    }
+
+    fn loop_(&mut self, body: &[Op]) -> bool {
+        // cmp byte [rbx + r12], 0; je end
+        self.emit(&[REX_X, 0x80, CELL_MODRM | 0b111_000, CELL_SIB, 0]);
+        let skip = self.jump(&[0x0f, 0x84]);
+        let start = self.code.len();
+        if !self.ops(body) {
+            return false
+        }
+        // cmp byte [rbx + r12], 0; jne start
+        self.emit(&[REX_X, 0x80, CELL_MODRM | 0b111_000, CELL_SIB, 0]);
+        let back = self.jump(&[0x0f, 0x85]);
+        self.patch(back, start);
+        let end = self.code.len();
+        self.patch(skip, end);
+        true
+    }
+}
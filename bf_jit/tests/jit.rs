@@ -0,0 +1,81 @@
+//! Checks that the JIT agrees with the interpreter.
+
+#![feature(io)]
+
+extern crate bf_jit;
+extern crate bf_interpreter;
+extern crate bf_ir;
+
+use std::old_io as io;
+use std::old_io::{BufReader, MemWriter};
+
+fn run(src: &str, input: &str) -> (Vec<u8>, Vec<u8>) {
+    let program = bf_jit::Program::parse(src.as_bytes()).unwrap();
+    let mut input = BufReader::new(input.as_bytes());
+    let mut out = MemWriter::new();
+    let tape = program.run(&mut input, &mut out).unwrap();
+    (tape, out.into_inner())
+}
+
+/// Check that the JIT and the interpreter agree on `src`.
+fn agree(src: &str, input: &str) {
+    let program = bf_interpreter::Program::parse(src.as_bytes()).unwrap();
+    let mut rdr = BufReader::new(input.as_bytes());
+    let mut out = MemWriter::new();
+    let tape = program.run(&mut rdr, &mut out).unwrap();
+
+    let (jit_tape, jit_out) = run(src, input);
+    assert_eq!(jit_out, out.into_inner());
+    assert!(jit_tape == tape);
+}
+
+#[test]
+fn hello_world() {
+    let src = "++++++++[>++++[>++>+++>+++>+<<<<-]>+>+>->>+[<]<-]>>.>\
+               ---.+++++++..+++.>>.<-.<.+++.------.--------.>>+.>++.";
+    assert_eq!(run(src, "").1, b"Hello World!\n");
+    agree(src, "");
+}
+
+#[test]
+fn semantics() {
+    // EOF, embedded input, wrapping, clamping at both ends, and
+    // optimised loops
+    agree(",.,+[-.,+]!ab", "cd");
+    agree("-.+.<<+.", "");
+    agree("+[>+]<[->>+<<]<.", "");
+    agree("<<<+>>+++[->++>+++<<]>.>.[-]<<<<--[>]", "");
+}
+
+#[test]
+fn write_error() {
+    struct Failing;
+    impl Writer for Failing {
+        fn write(&mut self, _: &[u8]) -> io::IoResult<()> {
+            Err(io::standard_error(io::BrokenPipe))
+        }
+    }
+
+    let program = bf_jit::Program::parse(b"+.+.").unwrap();
+    let result = program.run(&mut BufReader::new(b""), &mut Failing);
+    assert_eq!(result.err().map(|e| e.kind), Some(io::BrokenPipe));
+}
+
+#[test]
+fn interpreted_dialect() {
+    // pbrain's procedures aren't compiled, so this falls back to the
+    // interpreter
+    let src = b"+(>++++++++[<++++++++>-]<.): [-]+:";
+    let program = bf_ir::parse_dialect(src, bf_ir::Dialect::Pbrain).unwrap();
+    let program = bf_jit::Program::from_ir(program);
+    assert!(!program.is_native());
+    let mut out = MemWriter::new();
+    program.run(&mut BufReader::new(b""), &mut out).unwrap();
+    assert_eq!(out.into_inner(), b"AA");
+}
+
+#[test]
+#[cfg(all(target_arch = "x86_64", unix))]
+fn native() {
+    assert!(bf_jit::Program::parse(b"+.").unwrap().is_native());
+}
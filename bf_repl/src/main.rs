@@ -0,0 +1,157 @@
+//! An interactive brainfuck REPL, keeping the tape and the pointer
+//! from one line to the next, with the same semantics as
+//! `brainfuck!`.
+
+#![feature(io, path)]
+
+extern crate bf_interpreter;
+
+use std::cmp;
+use std::default::Default;
+use std::old_io as io;
+use std::old_io::MemWriter;
+
+use bf_interpreter::{Config, Error, Program, State, Stats};
+
+const HELP: &'static str = "\
+Type brainfuck to run it on the tape, which is kept between lines.
+Anything after a `!` is the input for `,`, which reads -1 once it runs
+out. A line with an unclosed `[` carries on to the next line.
+
+Commands:
+    :tape [<start>..<end>]   show the cells from <start> up to <end>
+    :reset                   clear the tape and move back to the start
+    :load <file.b>           run a program from a file
+    :help                    print this message
+    :quit                    exit (as does the end of the input)";
+
+/// The number of cells shown after each line.
+const WINDOW: usize = 10;
+
+struct Repl {
+    config: Config,
+    state: State,
+}
+
+impl Repl {
+    /// Run `src`, then print its output and the cells around the
+    /// pointer.
+    fn run(&mut self, src: &[u8]) {
+        let program = match Program::parse(src) {
+            Ok(program) => program,
+            Err(e) => return println!("error: {}", e)
+        };
+
+        let mut out = MemWriter::new();
+        let result = program.run_on(&mut self.state, &self.config, &mut Stats::default(),
+                                    &mut io::util::NullReader, &mut out);
+        let out = out.into_inner();
+        if !out.is_empty() {
+            println!("{}", String::from_utf8_lossy(&*out));
+        }
+        if let Err(e) = result {
+            println!("error: {}", e)
+        }
+
+        self.show_window();
+    }
+
+    /// Print the cells around the pointer.
+    fn show_window(&self) {
+        let pointer = self.state.pointer;
+        let start = if pointer > WINDOW / 2 { pointer - WINDOW / 2 } else { 0 };
+        self.show(start, start + WINDOW)
+    }
+
+    /// Print the cells in `start..end` (or as many as there are),
+    /// with the current one in brackets.
+    fn show(&self, start: usize, end: usize) {
+        let end = cmp::min(end, self.state.tape.len());
+        let mut line = format!("{}..{}:", start, end);
+        for i in start..end {
+            if i == self.state.pointer {
+                line.push_str(&*format!(" [{}]", self.state.tape[i]))
+            } else {
+                line.push_str(&*format!(" {}", self.state.tape[i]))
+            }
+        }
+        println!("{}", line)
+    }
+
+    /// Run a `:` command, returning false to quit.
+    fn command(&mut self, line: &str) -> bool {
+        let (name, arg) = match line.find(' ') {
+            Some(i) => (&line[..i], line[i + 1..].trim()),
+            None => (line, "")
+        };
+        match name {
+            ":tape" if arg.is_empty() => self.show_window(),
+            ":tape" => match range(arg) {
+                Some((start, end)) => self.show(start, end),
+                None => println!("error: expected a range like `0..20`")
+            },
+            ":reset" => {
+                self.state = State::new(&self.config);
+                println!("reset")
+            }
+            ":load" if !arg.is_empty() => {
+                match io::File::open(&Path::new(arg)).read_to_end() {
+                    Ok(src) => self.run(&*src),
+                    Err(e) => println!("error: couldn't read `{}`: {}", arg, e)
+                }
+            }
+            ":help" => println!("{}", HELP),
+            ":quit" | ":q" => return false,
+            _ => println!("error: unknown command `{}`, try `:help`", line)
+        }
+        true
+    }
+}
+
+/// Parse `start..end`.
+fn range(s: &str) -> Option<(usize, usize)> {
+    let i = match s.find("..") {
+        Some(i) => i,
+        None => return None
+    };
+    match (s[..i].trim().parse(), s[i + 2..].trim().parse()) {
+        (Ok(start), Ok(end)) if start <= end => Some((start, end)),
+        _ => None
+    }
+}
+
+fn main() {
+    let config: Config = Default::default();
+    let mut repl = Repl { state: State::new(&config), config: config };
+    let mut stdin = io::stdin();
+    // the lines of a program with unclosed loops so far
+    let mut pending = String::new();
+
+    println!("brainfuck REPL, type `:help` for help");
+    loop {
+        print!("{}", if pending.is_empty() { "bf> " } else { "... " });
+        io::stdio::flush();
+        let line = match stdin.read_line() {
+            Ok(line) => line,
+            Err(_) => break
+        };
+        let line = line.trim_right();
+
+        if pending.is_empty() && line.trim().starts_with(":") {
+            if !repl.command(line.trim()) { break }
+            continue
+        }
+
+        pending.push_str(line);
+        pending.push('\n');
+        match Program::parse(pending.as_bytes()) {
+            // wait for the rest of the loop
+            Err(Error::Unclosed(_)) => {}
+            _ => {
+                let src = pending.clone();
+                pending.clear();
+                repl.run(src.as_bytes())
+            }
+        }
+    }
+}
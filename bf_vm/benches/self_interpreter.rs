@@ -0,0 +1,40 @@
+//! The VM, the naive interpreter and `brainfuck!` running the
+//! brainfuck self-interpreter from `bf_bf_interpreter` on hello
+//! world.
+
+#![feature(io, test)]
+
+extern crate test;
+extern crate bf_vm;
+extern crate bf_interpreter;
+extern crate bf_bf_interpreter;
+
+use std::old_io::{BufReader, MemWriter};
+
+static INPUT: &'static [u8] =
+    b"++++++++[>++++[>++>+++>+++>+<<<<-]>+>+>->>+[<]<-]>>.>\
+      ---.+++++++..+++.>>.<-.<.+++.------.--------.>>+.>++.!";
+
+#[bench]
+fn vm(b: &mut test::Bencher) {
+    let program = bf_vm::Program::parse(bf_bf_interpreter::SOURCE).unwrap();
+    b.iter(|| {
+        program.run(&mut BufReader::new(INPUT), &mut MemWriter::new()).unwrap()
+    })
+}
+
+#[bench]
+fn interpreter(b: &mut test::Bencher) {
+    let program = bf_interpreter::Program::parse(bf_bf_interpreter::SOURCE).unwrap();
+    b.iter(|| {
+        program.run(&mut BufReader::new(INPUT), &mut MemWriter::new()).unwrap()
+    })
+}
+
+#[bench]
+fn brainfuck_macro(b: &mut test::Bencher) {
+    let bf = bf_bf_interpreter::bf();
+    b.iter(|| {
+        bf(&mut BufReader::new(INPUT), &mut MemWriter::new()).unwrap()
+    })
+}
@@ -0,0 +1,234 @@
+//! A bytecode virtual machine for brainfuck programs that are only
+//! known at runtime, sitting between `bf_interpreter` and `bf_jit`:
+//! it's portable, but runs the optimised IR as compact bytecode, with
+//! runs of `+`/`-` and `<`/`>` combined, the jumps for loops worked
+//! out in advance, and single instructions for clear, scan and
+//! multiplication loops. The semantics are the same as `brainfuck!`.
+
+#![crate_name="bf_vm"]
+#![crate_type="lib"]
+#![feature(io, core)]
+
+extern crate bf_ir;
+
+use std::{cmp, i32};
+use std::old_io as io;
+
+use bf_ir::{opt, Op};
+
+pub use bf_ir::Error;
+
+/// The number of cells on the tape.
+pub const TAPE_LEN: usize = 30_000;
+
+/// A single instruction, which fits in 8 bytes.
+#[derive(Clone, Copy, PartialEq, Debug)]
+enum Insn {
+    Add(u8),
+    /// Move the pointer, clamped at the ends of the tape.
+    Move(i32),
+    In,
+    Out,
+    Clear,
+    /// Move by the given amount until reaching a zero cell.
+    Scan(i32),
+    /// Run the multiplication with the given index, jumping over the
+    /// loop after it (which is only run when the multiplication would
+    /// go off the ends of the tape).
+    Mul(u32),
+    /// `[`: jump forward by the given amount if the cell is zero.
+    Jz(u32),
+    /// `]`: jump back by the given amount if the cell is non-zero.
+    Jnz(u32),
+    /// The end of the program.
+    Halt,
+}
+
+struct Mul {
+    /// How far the loop goes to the left and right of the starting
+    /// cell.
+    lo: usize,
+    hi: usize,
+    /// The offset of each cell that changes, and the multiple of the
+    /// current cell added to it.
+    targets: Vec<(isize, u8)>,
+    /// How far to jump to get past the loop.
+    skip: usize,
+}
+
+struct Compiler {
+    code: Vec<Insn>,
+    muls: Vec<Mul>,
+}
+
+impl Compiler {
+    fn ops(&mut self, ops: &[Op]) -> Result<(), Error> {
+        for op in ops.iter() {
+            match *op {
+                Op::Add(n) => self.code.push(Insn::Add(n as u8)),
+                Op::Move(n) => self.move_(n),
+                Op::In => self.code.push(Insn::In),
+                Op::Out => self.code.push(Insn::Out),
+                Op::Clear => self.code.push(Insn::Clear),
+                Op::Loop(_, ref body) => try!(self.loop_(&**body)),
+                Op::Scan(n) if -(i32::MAX as isize) <= n && n <= i32::MAX as isize => {
+                    self.code.push(Insn::Scan(n as i32))
+                }
+                Op::Scan(n) => try!(self.loop_(&[Op::Move(n)])),
+                Op::Mul(_, ref targets, ref body) => {
+                    let (lo, hi) = opt::visited(&**body);
+                    let index = self.muls.len();
+                    self.muls.push(Mul {
+                        lo: -lo as usize,
+                        hi: hi as usize,
+                        targets: targets.iter().map(|&(off, n)| (off, n as u8)).collect(),
+                        skip: 0,
+                    });
+                    let start = self.code.len();
+                    self.code.push(Insn::Mul(index as u32));
+                    try!(self.loop_(&**body));
+                    self.muls[index].skip = self.code.len() - start;
+                }
+                Op::Debug(_) => {}
+                _ => return Err(Error::Unsupported)
+            }
+        }
+        Ok(())
+    }
+
+    fn move_(&mut self, mut n: isize) {
+        // moves in the same direction can be split up without
+        // changing where they clamp
+        while n != 0 {
+            let step = cmp::max(cmp::min(n, i32::MAX as isize), -(i32::MAX as isize));
+            self.code.push(Insn::Move(step as i32));
+            n -= step;
+        }
+    }
+
+    fn loop_(&mut self, body: &[Op]) -> Result<(), Error> {
+        let open = self.code.len();
+        self.code.push(Insn::Jz(0));
+        try!(self.ops(body));
+        let close = self.code.len();
+        self.code[open] = Insn::Jz((close + 1 - open) as u32);
+        self.code.push(Insn::Jnz((close - open - 1) as u32));
+        Ok(())
+    }
+}
+
+pub struct Program {
+    code: Vec<Insn>,
+    muls: Vec<Mul>,
+    /// The input embedded after the first `!`.
+    input: Vec<u8>,
+}
+
+impl Program {
+    /// Parse and compile `src`, in the same format as
+    /// `bf_interpreter::Program::parse`.
+    pub fn parse(src: &[u8]) -> Result<Program, Error> {
+        let mut program = try!(bf_ir::parse(src));
+        program.ops = opt::optimise(program.ops, &*opt::passes(opt::MAX_LEVEL), false);
+        Program::from_ir(program)
+    }
+
+    /// Compile a program, which can only contain the plain brainfuck
+    /// ops and those from `bf_ir::opt` (any `Debug`s are ignored),
+    /// or else fails with `Error::Unsupported`.
+    pub fn from_ir(program: bf_ir::Program) -> Result<Program, Error> {
+        let mut compiler = Compiler { code: vec![], muls: vec![] };
+        try!(compiler.ops(&*program.ops));
+        compiler.code.push(Insn::Halt);
+        Ok(Program { code: compiler.code, muls: compiler.muls, input: program.input })
+    }
+
+    /// Run the program, returning the final tape, just like the
+    /// functions generated by `brainfuck!`.
+    pub fn run(&self, r: &mut Reader, w: &mut Writer) -> io::IoResult<Vec<u8>> {
+        let mut tape = vec![0u8; TAPE_LEN];
+        let len = tape.len();
+        let mut i = 0;
+        let mut input = &*self.input;
+        let mut pc = 0;
+        loop {
+            // the code always ends with `Halt`, and every jump stays
+            // inside it
+            match *unsafe { self.code.get_unchecked(pc) } {
+                Insn::Add(d) => tape[i] += d,
+                Insn::Move(n) if n < 0 => {
+                    let n = -n as usize;
+                    i = if i > n { i - n } else { 0 };
+                }
+                Insn::Move(n) => {
+                    let n = n as usize;
+                    i = if i + n < len { i + n } else { len - 1 };
+                }
+                Insn::In => {
+                    tape[i] = if !input.is_empty() {
+                        let b = input[0];
+                        input = &input[1..];
+                        b
+                    } else {
+                        match r.read_byte() {
+                            Ok(b) => b,
+                            Err(io::IoError { kind: io::EndOfFile, .. }) => -1,
+                            Err(e) => return Err(e)
+                        }
+                    }
+                }
+                Insn::Out => try!(w.write(&[tape[i]])),
+                Insn::Clear => tape[i] = 0,
+                // if there's no zero cell to find, the pointer gets
+                // stuck at the end of the tape, on a non-zero cell,
+                // forever
+                Insn::Scan(1) => {
+                    match tape[i..].iter().position(|&c| c == 0) {
+                        Some(j) => i += j,
+                        None => loop {}
+                    }
+                }
+                Insn::Scan(-1) => {
+                    match tape[..i + 1].iter().rposition(|&c| c == 0) {
+                        Some(j) => i = j,
+                        None => loop {}
+                    }
+                }
+                Insn::Scan(n) => {
+                    while tape[i] != 0 {
+                        i = if n < 0 {
+                            let n = -n as usize;
+                            if i > n { i - n } else { 0 }
+                        } else {
+                            let n = n as usize;
+                            if i + n < len { i + n } else { len - 1 }
+                        };
+                    }
+                }
+                Insn::Mul(index) => {
+                    let mul = &self.muls[index as usize];
+                    if i >= mul.lo && i + mul.hi < len {
+                        let c = tape[i];
+                        for &(off, n) in mul.targets.iter() {
+                            tape[(i as isize + off) as usize] += c * n;
+                        }
+                        tape[i] = 0;
+                        pc += mul.skip;
+                        continue
+                    }
+                }
+                Insn::Jz(off) => if tape[i] == 0 {
+                    pc += off as usize;
+                    continue
+                },
+                Insn::Jnz(off) => if tape[i] != 0 {
+                    pc -= off as usize;
+                    continue
+                },
+                Insn::Halt => break,
+            }
+            pc += 1;
+        }
+        Ok(tape)
+    }
+}
@@ -0,0 +1,83 @@
+//! Checks that the VM agrees with the interpreter.
+
+#![feature(io)]
+
+extern crate bf_vm;
+extern crate bf_interpreter;
+extern crate bf_ir;
+extern crate bf_bf_interpreter;
+
+use std::old_io as io;
+use std::old_io::{BufReader, MemWriter};
+
+fn run(src: &[u8], input: &[u8]) -> (Vec<u8>, Vec<u8>) {
+    let program = bf_vm::Program::parse(src).unwrap();
+    let mut input = BufReader::new(input);
+    let mut out = MemWriter::new();
+    let tape = program.run(&mut input, &mut out).unwrap();
+    (tape, out.into_inner())
+}
+
+/// Check that the VM and the interpreter agree on `src`.
+fn agree(src: &[u8], input: &[u8]) {
+    let program = bf_interpreter::Program::parse(src).unwrap();
+    let mut rdr = BufReader::new(input);
+    let mut out = MemWriter::new();
+    let tape = program.run(&mut rdr, &mut out).unwrap();
+
+    let (vm_tape, vm_out) = run(src, input);
+    assert_eq!(vm_out, out.into_inner());
+    assert!(vm_tape == tape);
+}
+
+static HELLO_WORLD: &'static [u8] =
+    b"++++++++[>++++[>++>+++>+++>+<<<<-]>+>+>->>+[<]<-]>>.>\
+      ---.+++++++..+++.>>.<-.<.+++.------.--------.>>+.>++.";
+
+#[test]
+fn hello_world() {
+    assert_eq!(run(HELLO_WORLD, b"").1, b"Hello World!\n");
+    agree(HELLO_WORLD, b"");
+}
+
+#[test]
+fn semantics() {
+    // EOF, embedded input, wrapping, clamping at both ends, and
+    // optimised loops
+    agree(b",.,+[-.,+]!ab", b"cd");
+    agree(b"-.+.<<+.", b"");
+    agree(b"+[>+]<[->>+<<]<.", b"");
+    agree(b"<<<+>>+++[->++>+++<<]>.>.[-]<<<<--[>]", b"");
+    agree(b">+>+>+<<[>]+<[<]>.>.>.>.>.", b"");
+    agree(b">+>>+>>+<<<<[>>]+<[<<]>.>.>.>.>.", b"");
+}
+
+#[test]
+fn self_interpreter() {
+    // the benchmark's workload
+    let mut input = HELLO_WORLD.to_vec();
+    input.push(b'!');
+    let src = bf_bf_interpreter::SOURCE;
+    assert_eq!(run(src, &*input).1, b"Hello World!\n");
+    agree(src, &*input);
+}
+
+#[test]
+fn write_error() {
+    struct Failing;
+    impl Writer for Failing {
+        fn write(&mut self, _: &[u8]) -> io::IoResult<()> {
+            Err(io::standard_error(io::BrokenPipe))
+        }
+    }
+
+    let program = bf_vm::Program::parse(b"+.+.").unwrap();
+    let result = program.run(&mut BufReader::new(b""), &mut Failing);
+    assert_eq!(result.err().map(|e| e.kind), Some(io::BrokenPipe));
+}
+
+#[test]
+fn unsupported() {
+    let program = bf_ir::parse_dialect(b"+(-):", bf_ir::Dialect::Pbrain).unwrap();
+    assert_eq!(bf_vm::Program::from_ir(program).err(), Some(bf_vm::Error::Unsupported));
+}
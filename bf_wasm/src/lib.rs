@@ -0,0 +1,208 @@
+//! Translating brainfuck programs into WebAssembly modules, in the
+//! text format, with the same semantics as `brainfuck!`: 30,000
+//! wrapping cells at the start of linear memory, `,` reading -1
+//! (255) at the end of the input, and the pointer clamped at the ends
+//! of the tape.
+//!
+//! The module imports two functions from `env`, which the host
+//! provides:
+//!
+//! - `read_byte: [] -> [i32]`, returning the next byte of input, or
+//!   -1 at the end of it,
+//! - `write_byte: [i32] -> []`, writing a byte of output,
+//!
+//! and exports its `memory` and a `run` function. Either import can
+//! trap to stop the program on an I/O error. After `run` returns,
+//! the first 30,000 bytes of memory are the final tape.
+
+#![crate_name="bf_wasm"]
+#![crate_type="lib"]
+
+extern crate bf_ir;
+
+use std::cmp;
+
+use bf_ir::{opt, Op};
+
+pub use bf_ir::Error;
+
+/// The number of cells on the tape.
+pub const TAPE_LEN: usize = 30_000;
+
+/// The size of a page of linear memory.
+const PAGE: usize = 65_536;
+
+/// Parse, optimise and translate `src`, in the same format as
+/// `bf_interpreter::Program::parse`.
+pub fn translate(src: &[u8]) -> Result<String, Error> {
+    let mut program = try!(bf_ir::parse(src));
+    program.ops = opt::optimise(program.ops, &*opt::passes(opt::MAX_LEVEL), false);
+    translate_ir(&program)
+}
+
+/// Translate a program, which can only contain the plain brainfuck
+/// ops and those from `bf_ir::opt` (any `Debug`s are ignored), or
+/// else fails with `Error::Unsupported`.
+pub fn translate_ir(program: &bf_ir::Program) -> Result<String, Error> {
+    let mut wat = Wat { out: String::new(), depth: 2, labels: 0 };
+    try!(wat.ops(&*program.ops));
+
+    // the embedded input goes straight after the tape
+    let input_end = TAPE_LEN + program.input.len();
+    let pages = (input_end + PAGE - 1) / PAGE;
+    Ok(format!("\
+(module
+  (import \"env\" \"read_byte\" (func $read_byte (result i32)))
+  (import \"env\" \"write_byte\" (func $write_byte (param i32)))
+
+  (memory (export \"memory\") {pages})
+  ;; the input embedded after the first `!`, read before `read_byte`
+  (data (i32.const {len}) \"{input}\")
+  (global $input (mut i32) (i32.const {len}))
+
+  (func $in (result i32)
+    (local $b i32)
+    (if (i32.lt_u (global.get $input) (i32.const {input_end}))
+      (then
+        (local.set $b (i32.load8_u (global.get $input)))
+        (global.set $input (i32.add (global.get $input) (i32.const 1)))
+        (return (local.get $b))))
+    (call $read_byte))
+
+  (func (export \"run\")
+    (local $i i32)
+    (local $c i32)
+{ops}  ))
+", pages = pages, len = TAPE_LEN, input = escape(&*program.input),
+       input_end = input_end, ops = wat.out))
+}
+
+/// `s` as the inside of a string, with everything but letters and
+/// digits as hex escapes.
+fn escape(s: &[u8]) -> String {
+    let mut out = String::new();
+    for &b in s.iter() {
+        if (b as char).is_alphanumeric() && b < 0x80 {
+            out.push(b as char)
+        } else {
+            out.push_str(&*format!("\\{:02x}", b))
+        }
+    }
+    out
+}
+
+/// The current cell.
+const CELL: &'static str = "(i32.load8_u (local.get $i))";
+
+struct Wat {
+    out: String,
+    /// The current indentation, in levels of two spaces.
+    depth: usize,
+    /// The number of labels used so far, to make new ones.
+    labels: usize,
+}
+
+impl Wat {
+    fn line(&mut self, line: &str) {
+        for _ in 0..self.depth {
+            self.out.push_str("  ")
+        }
+        self.out.push_str(line);
+        self.out.push('\n')
+    }
+
+    fn ops(&mut self, ops: &[Op]) -> Result<(), Error> {
+        for op in ops.iter() {
+            try!(self.op(op))
+        }
+        Ok(())
+    }
+
+    fn op(&mut self, op: &Op) -> Result<(), Error> {
+        match *op {
+            Op::Add(n) => {
+                self.line(&*format!("(i32.store8 (local.get $i) (i32.add {} (i32.const {})))",
+                                    CELL, n as u8))
+            }
+            Op::Move(n) => self.move_(n),
+            Op::In => self.line("(i32.store8 (local.get $i) (call $in))"),
+            Op::Out => self.line(&*format!("(call $write_byte {})", CELL)),
+            Op::Clear => self.line("(i32.store8 (local.get $i) (i32.const 0))"),
+            Op::Loop(_, ref body) => try!(self.loop_(&**body)),
+            Op::Scan(n) => try!(self.loop_(&[Op::Move(n)])),
+            // multiplication loops like `[->+>++<<]`, unless they
+            // would go off the ends of the tape, in which case the
+            // original loop is run so that the pointer is clamped
+            // the same way
+            Op::Mul(_, ref targets, ref body) => {
+                let (lo, hi) = opt::visited(&**body);
+                let in_bounds = format!("(i32.lt_u (i32.add (local.get $i) (i32.const {})) \
+                                         (i32.const {}))", hi, TAPE_LEN);
+                let in_bounds = if lo < 0 {
+                    format!("(i32.and (i32.ge_u (local.get $i) (i32.const {})) {})",
+                            -lo, in_bounds)
+                } else {
+                    in_bounds
+                };
+                self.line(&*format!("(if {}", CELL));
+                self.depth += 1;
+                self.line("(then");
+                self.depth += 1;
+                self.line(&*format!("(if {}", in_bounds));
+                self.depth += 1;
+                self.line("(then");
+                self.depth += 1;
+                self.line(&*format!("(local.set $c {})", CELL));
+                for &(off, n) in targets.iter() {
+                    let target = if off < 0 {
+                        format!("(i32.sub (local.get $i) (i32.const {}))", -off)
+                    } else {
+                        format!("(i32.add (local.get $i) (i32.const {}))", off)
+                    };
+                    self.line(&*format!("(i32.store8 {0} (i32.add (i32.load8_u {0}) \
+                                         (i32.mul (local.get $c) (i32.const {1}))))",
+                                        target, n as u8));
+                }
+                self.line("(i32.store8 (local.get $i) (i32.const 0)))");
+                self.depth -= 1;
+                self.line("(else");
+                self.depth += 1;
+                try!(self.loop_(&**body));
+                self.line("))))");
+                self.depth -= 4;
+            }
+            Op::Debug(_) => {}
+            _ => return Err(Error::Unsupported)
+        }
+        Ok(())
+    }
+
+    fn loop_(&mut self, body: &[Op]) -> Result<(), Error> {
+        let label = self.labels;
+        self.labels += 1;
+        self.line(&*format!("(block $end{}", label));
+        self.depth += 1;
+        self.line(&*format!("(loop $start{}", label));
+        self.depth += 1;
+        self.line(&*format!("(br_if $end{} (i32.eqz {}))", label, CELL));
+        try!(self.ops(body));
+        self.line(&*format!("(br $start{})))", label));
+        self.depth -= 2;
+        Ok(())
+    }
+
+    fn move_(&mut self, n: isize) {
+        // moving further than the length of the tape clamps the same
+        // way as moving exactly that far, and keeps the numbers small
+        if n < 0 {
+            let n = cmp::min(-n as usize, TAPE_LEN);
+            self.line(&*format!("(local.set $i (select (i32.sub (local.get $i) (i32.const {0})) \
+                                 (i32.const 0) (i32.gt_u (local.get $i) (i32.const {0}))))", n))
+        } else {
+            let n = cmp::min(n as usize, TAPE_LEN);
+            self.line(&*format!("(local.set $i (select (i32.add (local.get $i) (i32.const {0})) \
+                                 (i32.const {1}) (i32.lt_u (i32.add (local.get $i) \
+                                 (i32.const {0})) (i32.const {2}))))", n, TAPE_LEN - 1, TAPE_LEN))
+        }
+    }
+}
@@ -0,0 +1,107 @@
+//! Validates the module for each program with `wasmparser`, runs it
+//! with `wasmi`, and checks that it gives the same output and tape as
+//! `bf_interpreter`.
+
+#![feature(io)]
+
+extern crate bf_wasm;
+extern crate bf_interpreter;
+extern crate bf_bf_interpreter;
+extern crate wasmi;
+extern crate wasmparser;
+extern crate wat;
+
+use std::old_io::{BufReader, MemWriter};
+
+use wasmi::{Caller, Engine, Linker, Module, Store};
+
+/// The input and output of a running module.
+struct Host {
+    input: Vec<u8>,
+    pos: usize,
+    out: Vec<u8>,
+}
+
+/// Validate the module for `src` and run it on `input`, returning the
+/// output and the tape.
+fn run_wasm(src: &[u8], input: &[u8]) -> (Vec<u8>, Vec<u8>) {
+    let wat = bf_wasm::translate(src).unwrap();
+    let wasm = wat::parse_str(&*wat).unwrap();
+    if let Err(e) = wasmparser::validate(&*wasm) {
+        panic!("invalid module: {}\n{}", e, wat)
+    }
+
+    let engine = Engine::default();
+    let module = Module::new(&engine, &*wasm).unwrap();
+    let host = Host { input: input.to_vec(), pos: 0, out: vec![] };
+    let mut store = Store::new(&engine, host);
+    let mut linker = Linker::new(&engine);
+    linker.func_wrap("env", "read_byte", |mut caller: Caller<Host>| -> i32 {
+        let host = caller.data_mut();
+        if host.pos < host.input.len() {
+            host.pos += 1;
+            host.input[host.pos - 1] as i32
+        } else {
+            -1
+        }
+    }).unwrap();
+    linker.func_wrap("env", "write_byte", |mut caller: Caller<Host>, b: i32| {
+        caller.data_mut().out.push(b as u8)
+    }).unwrap();
+    let instance = linker.instantiate(&mut store, &module).unwrap().start(&mut store).unwrap();
+
+    let run = instance.get_typed_func::<(), ()>(&store, "run").unwrap();
+    run.call(&mut store, ()).unwrap();
+    let memory = instance.get_memory(&store, "memory").unwrap();
+    let tape = memory.data(&store)[..bf_wasm::TAPE_LEN].to_vec();
+    (store.into_data().out, tape)
+}
+
+/// Check that the module for `src` and the interpreter agree on
+/// `input`, and that the output is `expected`.
+fn agree(src: &[u8], input: &[u8], expected: &[u8]) {
+    let program = bf_interpreter::Program::parse(src).unwrap();
+    let mut out = MemWriter::new();
+    let tape = program.run(&mut BufReader::new(input), &mut out).unwrap();
+    let out = out.into_inner();
+    assert_eq!(&*out, expected);
+
+    let (wasm_out, wasm_tape) = run_wasm(src, input);
+    assert_eq!(wasm_out, out);
+    assert!(wasm_tape == tape);
+}
+
+static HELLO_WORLD: &'static [u8] =
+    b"++++++++[>++++[>++>+++>+++>+<<<<-]>+>+>->>+[<]<-]>>.>\
+      ---.+++++++..+++.>>.<-.<.+++.------.--------.>>+.>++.";
+
+#[test]
+fn hello_world() {
+    agree(HELLO_WORLD, b"", b"Hello World!\n");
+}
+
+#[test]
+fn io() {
+    agree(b",+[-.,+]", b"Hello", b"Hello");
+    agree(b",.", b"", &[255]);
+    agree(b",+[-.,+]!Hello, \"?\\", b"world", b"Hello, \"?\\world");
+}
+
+#[test]
+fn semantics() {
+    agree(b"-.+.", b"", &[255, 0]);
+    agree(b"<<+.", b"", &[1]);
+    // fills the tape with 1s, until the last cell wraps around to 0
+    agree(b"+[>+]<.", b"", &[1]);
+    agree(b"+++[->++>+++<<]>.>.", b"", &[6, 9]);
+    // the multiplication starts next to the end of the tape
+    agree(b"+[>+]<[->>+<<]<.", b"", &[1]);
+    agree(b">+>+>+<<[>]+<[<]>.>.>.>.>.", b"", &[1, 1, 1, 1, 0]);
+}
+
+#[test]
+fn self_interpreter() {
+    let mut input = HELLO_WORLD.to_vec();
+    input.push(b'!');
+    agree(bf_bf_interpreter::SOURCE, &*input, b"Hello World!\n");
+}
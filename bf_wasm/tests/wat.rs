@@ -0,0 +1,76 @@
+extern crate bf_wasm;
+extern crate bf_ir;
+
+/// Check that the parentheses in `wat` are balanced (ignoring the
+/// insides of strings and comments), as a rough check that it's
+/// well-formed.
+fn balanced(wat: &str) -> bool {
+    let mut depth = 0;
+    let (mut string, mut comment, mut escaped) = (false, false, false);
+    for c in wat.chars() {
+        if comment {
+            comment = c != '\n';
+        } else if string {
+            match c {
+                _ if escaped => escaped = false,
+                '\\' => escaped = true,
+                '"' => string = false,
+                _ => {}
+            }
+        } else {
+            match c {
+                '"' => string = true,
+                ';' => comment = true,
+                '(' => depth += 1,
+                ')' if depth == 0 => return false,
+                ')' => depth -= 1,
+                _ => {}
+            }
+        }
+    }
+    depth == 0 && !string
+}
+
+#[test]
+fn module() {
+    let wat = bf_wasm::translate(b"++++++++[>++++[>++>+++>+++>+<<<<-]>+>+>->>+[<]<-]>>.>\
+                                   ---.+++++++..+++.>>.<-.<.+++.------.--------.>>+.>++.")
+        .unwrap();
+    assert!(wat.starts_with("(module\n"));
+    assert!(wat.contains("(import \"env\" \"read_byte\" (func $read_byte (result i32)))"));
+    assert!(wat.contains("(import \"env\" \"write_byte\" (func $write_byte (param i32)))"));
+    assert!(wat.contains("(memory (export \"memory\") 1)"));
+    assert!(wat.contains("(func (export \"run\")"));
+    assert!(balanced(&*wat));
+}
+
+#[test]
+fn loops() {
+    // clear, scan and multiplication loops, with every label unique
+    let wat = bf_wasm::translate(b"+[-]>+[<]+++[->++>+++<<]-[>+<-]").unwrap();
+    assert!(balanced(&*wat));
+    for label in ["$end0", "$end1", "$end2"].iter() {
+        assert_eq!(wat.split(&*format!("(block {}\n", label)).count(), 2);
+    }
+    assert!(!wat.contains("$end3"));
+}
+
+#[test]
+fn embedded_input() {
+    let wat = bf_wasm::translate(b",+[-.,+]!ab \"c)").unwrap();
+    assert!(wat.contains("(data (i32.const 30000) \"ab\\20\\22c\\29\")"));
+    assert!(wat.contains("(i32.const 30006)"));
+    assert!(balanced(&*wat));
+
+    // enough memory for the tape and the input
+    let mut long = b",!".to_vec();
+    long.extend(::std::iter::repeat(b'a').take(40_000));
+    assert!(bf_wasm::translate(&*long).unwrap().contains("(memory (export \"memory\") 2)"));
+}
+
+#[test]
+fn errors() {
+    assert_eq!(bf_wasm::translate(b"+]").err(), Some(bf_wasm::Error::Unmatched(1)));
+    let program = bf_ir::parse_dialect(b"+(-):", bf_ir::Dialect::Pbrain).unwrap();
+    assert_eq!(bf_wasm::translate_ir(&program).err(), Some(bf_wasm::Error::Unsupported));
+}
@@ -0,0 +1,190 @@
+//! User-defined words for the eight brainfuck commands, like
+//! `brainfuck!(alphabet { inc = foo, dec = bar }; ...)`, for the many
+//! dialects that are just renamings.
+
+use syntax::ast;
+use syntax::codemap;
+use syntax::ext::base::ExtCtxt;
+use syntax::parse::token;
+
+use ir::Op;
+
+#[derive(Clone, Copy, PartialEq)]
+pub enum Command {
+    Inc,
+    Dec,
+    Left,
+    Right,
+    Out,
+    In,
+    Open,
+    Close,
+}
+
+/// The names of the commands in `alphabet { ... }`.
+static COMMANDS: &'static [(&'static str, Command)] = &[
+    ("inc", Command::Inc),
+    ("dec", Command::Dec),
+    ("left", Command::Left),
+    ("right", Command::Right),
+    ("out", Command::Out),
+    ("in", Command::In),
+    ("open", Command::Open),
+    ("close", Command::Close),
+];
+
+impl Command {
+    /// The op for the command, or `None` for the loop brackets,
+    /// which have to be matched up.
+    pub fn op(self) -> Option<Op> {
+        match self {
+            Command::Inc => Some(Op::Add(1)),
+            Command::Dec => Some(Op::Add(-1)),
+            Command::Left => Some(Op::Move(-1)),
+            Command::Right => Some(Op::Move(1)),
+            Command::Out => Some(Op::Out),
+            Command::In => Some(Op::In),
+            Command::Open | Command::Close => None
+        }
+    }
+
+    /// How the command is usually written.
+    fn token(self) -> token::Token {
+        match self {
+            Command::Inc => token::BinOp(token::Plus),
+            Command::Dec => token::BinOp(token::Minus),
+            Command::Left => token::Lt,
+            Command::Right => token::Gt,
+            Command::Out => token::Dot,
+            Command::In => token::Comma,
+            Command::Open => token::OpenDelim(token::Bracket),
+            Command::Close => token::CloseDelim(token::Bracket),
+        }
+    }
+}
+
+pub struct Alphabet {
+    /// The span of the whole `{ ... }`.
+    pub sp: codemap::Span,
+    /// Each word, as a sequence of tokens, and the command it stands
+    /// for.
+    words: Vec<(Vec<token::Token>, Command)>,
+}
+
+impl Alphabet {
+    /// The longest word at the start of `toks`, and its length in
+    /// tokens.
+    pub fn find(&self, toks: &[(codemap::Span, token::Token)]) -> Option<(usize, Command)> {
+        let mut best = None;
+        for &(ref word, command) in self.words.iter() {
+            if word.len() > toks.len() { continue }
+            if best.map_or(false, |(len, _)| len >= word.len()) { continue }
+            if word.iter().zip(toks.iter()).all(|(a, &(_, ref b))| same_token(a, b)) {
+                best = Some((word.len(), command))
+            }
+        }
+        best
+    }
+
+    /// Whether `tok` appears in any word.
+    pub fn contains(&self, tok: &token::Token) -> bool {
+        self.words.iter().any(|&(ref word, _)| word.iter().any(|t| same_token(t, tok)))
+    }
+}
+
+/// Compare tokens, ignoring the hygiene of identifiers (which can't
+/// be compared directly across expansions).
+fn same_token(a: &token::Token, b: &token::Token) -> bool {
+    match (a, b) {
+        (&token::Ident(a, _), &token::Ident(b, _)) => a.name == b.name,
+        (&token::Lifetime(a), &token::Lifetime(b)) => a.name == b.name,
+        (&token::Ident(..), _) | (_, &token::Ident(..)) => false,
+        (&token::Lifetime(..), _) | (_, &token::Lifetime(..)) => false,
+        _ => a == b
+    }
+}
+
+fn same_word(a: &[token::Token], b: &[token::Token]) -> bool {
+    a.len() == b.len() && a.iter().zip(b.iter()).all(|(a, b)| same_token(a, b))
+}
+
+/// Parse the `{ command = word, ... }` value of the `alphabet` option,
+/// where each word is one or more tokens. Commands that aren't given
+/// keep their usual token, unless it's used as another word.
+pub fn parse(cx: &ExtCtxt, sp: codemap::Span,
+             value: Option<&ast::TokenTree>) -> Option<Alphabet> {
+    let (sp, tts) = match value {
+        Some(&ast::TtDelimited(sp, ref delimed)) if delimed.delim == token::Brace => {
+            (sp, &*delimed.tts)
+        }
+        _ => {
+            cx.span_err(value.map_or(sp, |tt| tt.get_span()),
+                        "`brainfuck!` option `alphabet` should be followed by \
+                         `{ command = word, ... }`");
+            return None
+        }
+    };
+
+    let mut words: Vec<(Vec<token::Token>, Command)> = vec![];
+    let entries = tts.split(|tt| match *tt {
+        ast::TtToken(_, token::Comma) => true,
+        _ => false
+    });
+    for entry in entries.filter(|entry| !entry.is_empty()) {
+        let (name_sp, command) = match entry[0] {
+            ast::TtToken(sp, token::Ident(id, _)) => {
+                let name = token::get_ident(id);
+                match COMMANDS.iter().find(|&&(n, _)| n == &*name) {
+                    Some(&(_, command)) => (sp, command),
+                    None => {
+                        cx.span_err(sp, &*format!("unknown brainfuck command `{}`, expected one \
+                                                   of `inc`, `dec`, `left`, `right`, `out`, \
+                                                   `in`, `open` or `close`", name));
+                        continue
+                    }
+                }
+            }
+            ref tt => {
+                cx.span_err(tt.get_span(), "expected a brainfuck command name");
+                continue
+            }
+        };
+        match entry.get(1) {
+            Some(&ast::TtToken(_, token::Eq)) => {}
+            _ => {
+                cx.span_err(name_sp, "expected `=` and a word after the command name");
+                continue
+            }
+        }
+
+        let mut word = vec![];
+        for tt in entry[2..].iter() {
+            match *tt {
+                ast::TtToken(_, ref tok) => word.push(tok.clone()),
+                ref tt => cx.span_err(tt.get_span(), "words can't contain delimited groups")
+            }
+        }
+        if word.is_empty() {
+            cx.span_err(name_sp, "expected a word after `=`");
+            continue
+        }
+        if words.iter().any(|&(_, c)| c == command) {
+            cx.span_err(name_sp, "command given more than once");
+            continue
+        }
+        if words.iter().any(|&(ref w, _)| same_word(&**w, &*word)) {
+            cx.span_err(entry[2].get_span(), "word used for more than one command");
+            continue
+        }
+        words.push((word, command))
+    }
+
+    for &(_, command) in COMMANDS.iter() {
+        if words.iter().any(|&(_, c)| c == command) { continue }
+        let word = vec![command.token()];
+        if !words.iter().any(|&(ref w, _)| same_word(&**w, &*word)) {
+            words.push((word, command))
+        }
+    }
+    Some(Alphabet { sp: sp, words: words })
+}
@@ -0,0 +1,607 @@
+//! Generating Rust code for brainfuck commands.
+
+use std::cell::{Cell, RefCell};
+use std::rc::Rc;
+
+use syntax::ast;
+use syntax::ptr::P;
+use syntax::codemap;
+use syntax::ext::base::ExtCtxt;
+use syntax::ext::build::AstBuilder;
+use syntax::parse::token;
+
+use bf_ir::opt;
+
+use ir::{self, Op};
+use options::{Options, Dialect, Sink};
+
+pub struct BF<'a> {
+    cx: &'a ExtCtxt<'a>,
+    opts: &'a Options,
+    array: P<ast::Expr>,
+    idx: P<ast::Expr>,
+    rdr: P<ast::Expr>,
+    wtr: P<ast::Expr>,
+    /// The functions for pbrain procedures, and how many there are.
+    procs: RefCell<Vec<P<ast::Item>>>,
+    num_procs: Cell<usize>,
+}
+
+/// A flattened instruction, for running a step at a time.
+enum Insn<'o> {
+    Op(&'o Op),
+    /// `[`, and the position of its `]`.
+    Open(usize),
+    /// `]`, and the position of its `[`.
+    Close(usize),
+}
+
+fn flatten<'o>(ops: &'o [Op], insns: &mut Vec<Insn<'o>>) {
+    for op in ops.iter() {
+        match *op {
+            Op::Loop(_, ref body) => {
+                let open = insns.len();
+                insns.push(Insn::Open(0));
+                flatten(&**body, insns);
+                let close = insns.len();
+                insns.push(Insn::Close(open));
+                insns[open] = Insn::Open(close);
+            }
+            ref op => insns.push(Insn::Op(op))
+        }
+    }
+}
+
+fn proc_name(n: usize) -> ast::Ident {
+    token::str_to_ident(&*format!("_proc_{}", n))
+}
+
+impl<'a> BF<'a> {
+    pub fn new(cx: &'a ExtCtxt<'a>, opts: &'a Options) -> BF<'a> {
+        let idx = if opts.dialect == Dialect::Pbrain {
+            // pbrain procedures are separate functions, so the
+            // index is passed around by reference.
+            quote_expr!(cx, (*_i))
+        } else {
+            quote_expr!(cx, _i)
+        };
+        BF {
+            array: quote_expr!(cx, _array),
+            idx: idx,
+            rdr: quote_expr!(cx, _r),
+            wtr: quote_expr!(cx, _w),
+            procs: RefCell::new(vec![]),
+            num_procs: Cell::new(0),
+            cx: cx,
+            opts: opts,
+        }
+    }
+
+    /// The function that runs the whole program, reading `input`
+    /// (if any) before anything from the real reader.
+    pub fn program(&self, sp: codemap::Span, ops: &[Op],
+                   input: Option<Vec<u8>>) -> P<ast::Expr> {
+        let run = match self.opts.dialect {
+            Dialect::Brainfork => self.threaded_program(sp, ops),
+            _ => self.single_program(sp, ops)
+        };
+        match input {
+            Some(input) => self.with_input(sp, run, input),
+            None => run
+        }
+    }
+
+    /// Wrap the function `run` so that it reads `input` first.
+    fn with_input(&self, sp: codemap::Span, run: P<ast::Expr>, input: Vec<u8>) -> P<ast::Expr> {
+        let input = self.cx.expr_lit(sp, ast::LitBinary(Rc::new(input)));
+        quote_expr!(self.cx, {
+            struct _Prefixed<'a> {
+                input: ::std::old_io::BufReader<'static>,
+                rest: &'a mut (Reader + 'a),
+            }
+            impl<'a> Reader for _Prefixed<'a> {
+                fn read(&mut self, buf: &mut [u8]) -> ::std::old_io::IoResult<usize> {
+                    match self.input.read(buf) {
+                        Err(ref e) if e.kind == ::std::old_io::EndOfFile => self.rest.read(buf),
+                        result => result
+                    }
+                }
+            }
+
+            fn run(_r: &mut Reader, _w: &mut Writer) -> ::std::old_io::IoResult<Vec<u8>> {
+                let _run = $run;
+                _run(&mut _Prefixed { input: ::std::old_io::BufReader::new($input), rest: _r }, _w)
+            }
+            run
+        })
+    }
+
+    fn single_program(&self, sp: codemap::Span, ops: &[Op]) -> P<ast::Expr> {
+        if self.opts.dialect.bits() {
+            return self.bits_program(sp, ops)
+        }
+
+        let mut core_code = self.ops_to_expr(sp, ops);
+        if self.opts.dialect == Dialect::Ebf1 {
+            // the storage register for `$` and `!`
+            core_code = quote_expr!(self.cx, {
+                let mut _storage = 0u8;
+                $core_code
+            })
+        }
+
+        if self.opts.dialect != Dialect::Pbrain {
+            return quote_expr!(self.cx, {
+                fn run(_r: &mut Reader, _w: &mut Writer) -> ::std::old_io::IoResult<Vec<u8>> {
+                    let mut _array = vec![0u8; 30_000];
+                    let mut _i = 0;
+                    $core_code;
+                    Ok(_array)
+                }
+                run
+            })
+        }
+
+        let procs = self.procs.borrow().clone();
+        let call = self.call_item(sp);
+        quote_expr!(self.cx, {
+            fn run(_r: &mut Reader, _w: &mut Writer) -> ::std::old_io::IoResult<Vec<u8>> {
+                $procs
+                $call
+                fn _main(_array: &mut [u8], _i: &mut usize, _procs: &mut [usize; 256],
+                         _r: &mut Reader, _w: &mut Writer) -> ::std::old_io::IoResult<()> {
+                    $core_code;
+                    Ok(())
+                }
+
+                let mut _array = vec![0u8; 30_000];
+                try!(_main(&mut *_array, &mut 0, &mut [0; 256], _r, _w));
+                Ok(_array)
+            }
+            run
+        })
+    }
+
+    /// A Boolfuck or Smallfuck program, where each cell is a single
+    /// bit, packed eight to a byte, least significant first.
+    fn bits_program(&self, sp: codemap::Span, ops: &[Op]) -> P<ast::Expr> {
+        let core_code = self.ops_to_expr(sp, ops);
+        if self.opts.dialect == Dialect::Smallfuck {
+            // no I/O at all
+            return quote_expr!(self.cx, {
+                fn run(_r: &mut Reader, _w: &mut Writer) -> ::std::old_io::IoResult<Vec<u8>> {
+                    let mut _array = vec![0u8; 30_000 / 8];
+                    let mut _i = 0;
+                    $core_code;
+                    Ok(_array)
+                }
+                run
+            })
+        }
+
+        quote_expr!(self.cx, {
+            fn run(_r: &mut Reader, _w: &mut Writer) -> ::std::old_io::IoResult<Vec<u8>> {
+                let mut _array = vec![0u8; 30_000 / 8];
+                let mut _i = 0;
+                // the bits of the last byte read that haven't been
+                // used yet, and the bits written that don't make up a
+                // whole byte yet.
+                let mut _in_byte = 0u8;
+                let mut _in_bits = 0;
+                let mut _out_byte = 0u8;
+                let mut _out_bits = 0;
+                $core_code;
+                // a partial byte is padded with zeros
+                if _out_bits > 0 {
+                    try!(_w.write(&[_out_byte]));
+                }
+                Ok(_array)
+            }
+            run
+        })
+    }
+
+    /// A Brainfork program, where the threads are run one instruction
+    /// at a time, in turn (in order of creation), so that the output
+    /// is deterministic.
+    fn threaded_program(&self, sp: codemap::Span, ops: &[Op]) -> P<ast::Expr> {
+        let mut insns = vec![];
+        flatten(ops, &mut insns);
+
+        let idx = &self.idx;
+        let array = &self.array;
+        let mut arms: Vec<_> = insns.iter().enumerate().map(|(n, insn)| {
+            let e = match *insn {
+                Insn::Op(&Op::Fork) => quote_expr!(self.cx, {
+                    // the child starts at the next instruction, one
+                    // cell to the right.
+                    let child = if $idx < $array.len() - 1 { $idx + 1 } else { $idx };
+                    $array[$idx] = 0;
+                    $array[child] = 1;
+                    _threads.push((_pc, child));
+                }),
+                Insn::Op(op) => self.op_to_expr(op),
+                Insn::Open(close) => {
+                    let after = close + 1;
+                    quote_expr!(self.cx, if $array[$idx] == 0 { _pc = $after })
+                }
+                Insn::Close(open) => {
+                    let after = open + 1;
+                    quote_expr!(self.cx, if $array[$idx] != 0 { _pc = $after })
+                }
+            };
+            self.cx.arm(sp, vec![self.cx.pat_lit(sp, self.cx.expr_usize(sp, n))], e)
+        }).collect();
+        // past the end, so the thread has finished
+        arms.push(self.cx.arm(sp, vec![self.cx.pat_wild(sp)], quote_expr!(self.cx, {
+            _threads.remove(_t);
+            continue
+        })));
+        let dispatch = self.cx.expr_match(sp, quote_expr!(self.cx, _current), arms);
+
+        quote_expr!(self.cx, {
+            fn run(_r: &mut Reader, _w: &mut Writer) -> ::std::old_io::IoResult<Vec<u8>> {
+                let mut _array = vec![0u8; 30_000];
+                // the next instruction and the index of each thread
+                let mut _threads: Vec<(usize, usize)> = vec![(0, 0)];
+                while !_threads.is_empty() {
+                    let mut _t = 0;
+                    while _t < _threads.len() {
+                        let (_current, mut _i) = _threads[_t];
+                        let mut _pc = _current + 1;
+                        $dispatch;
+                        _threads[_t] = (_pc, _i);
+                        _t += 1;
+                    }
+                }
+                Ok(_array)
+            }
+            run
+        })
+    }
+
+    /// pbrain's dispatch table: call the procedure numbered by the
+    /// current cell, failing if there isn't one.
+    fn call_item(&self, sp: codemap::Span) -> P<ast::Item> {
+        let mut arms: Vec<_> = (1..self.num_procs.get() + 1).map(|n| {
+            let name = proc_name(n);
+            self.cx.arm(sp, vec![self.cx.pat_lit(sp, self.cx.expr_usize(sp, n))],
+                        quote_expr!(self.cx, $name(_array, _i, _procs, _r, _w)))
+        }).collect();
+        arms.push(self.cx.arm(sp, vec![self.cx.pat_wild(sp)], quote_expr!(self.cx, {
+            Err(::std::old_io::IoError {
+                kind: ::std::old_io::OtherIoError,
+                desc: "pbrain: call to an undefined procedure",
+                detail: Some(format!("procedure {}", _array[*_i]))
+            })
+        })));
+        let dispatch = self.cx.expr_match(sp, quote_expr!(self.cx, _procs[_array[*_i] as usize]),
+                                          arms);
+
+        quote_item!(self.cx,
+            fn _call(_array: &mut [u8], _i: &mut usize, _procs: &mut [usize; 256],
+                     _r: &mut Reader, _w: &mut Writer) -> ::std::old_io::IoResult<()> {
+                $dispatch
+            }
+        ).unwrap()
+    }
+
+    fn ops_to_expr(&self, sp: codemap::Span, ops: &[Op]) -> P<ast::Expr> {
+        let v = ops.iter()
+            .map(|op| self.cx.stmt_expr(self.op_to_expr(op)))
+            .collect();
+
+        let block = self.cx.block(sp, v, None);
+        self.cx.expr_block(block)
+    }
+
+    fn op_to_expr(&self, op: &Op) -> P<ast::Expr> {
+        let idx = &self.idx;
+        let array = &self.array;
+        match *op {
+            Op::Add(n) => self.add_expr(n),
+            Op::Move(n) => self.move_expr(n),
+            Op::In if self.opts.dialect.bits() => self.input_bit_expr(),
+            Op::Out if self.opts.dialect.bits() => self.output_bit_expr(),
+            Op::In => self.input_expr(),
+            Op::Out => self.output_expr(),
+            Op::Loop(pos, ref body) => {
+                let centre = self.ops_to_expr(ir::span(pos), &**body);
+                self.loop_expr(centre)
+            }
+            Op::Proc(pos, ref body) => self.proc_expr(ir::span(pos), &**body),
+            Op::Call => quote_expr!(self.cx, try!(_call(_array, _i, _procs, _r, _w))),
+            Op::Debug(pos) => self.debug_expr(ir::span(pos)),
+            Op::End => quote_expr!(self.cx, return Ok(_array)),
+            Op::Store => quote_expr!(self.cx, _storage = $array[$idx]),
+            Op::Load => quote_expr!(self.cx, $array[$idx] = _storage),
+            Op::ShiftRight => quote_expr!(self.cx, $array[$idx] >>= 1),
+            Op::ShiftLeft => quote_expr!(self.cx, $array[$idx] <<= 1),
+            Op::Not => quote_expr!(self.cx, $array[$idx] = !$array[$idx]),
+            Op::Flip => quote_expr!(self.cx, $array[$idx / 8] ^= 1 << ($idx % 8)),
+            Op::Clear => quote_expr!(self.cx, $array[$idx] = 0),
+            Op::Scan(n) => self.scan_expr(n),
+            Op::Mul(pos, ref targets, ref body) => {
+                self.mul_expr(ir::span(pos), &**targets, &**body)
+            }
+            Op::Fork => self.cx.span_bug(codemap::DUMMY_SP,
+                                         "brainfuck: `Y` outside a Brainfork program"),
+        }
+    }
+
+    // `<` and `>`, possibly several at once, clamped at the ends of
+    // the tape (or, in Smallfuck, ending the program there).
+    fn move_expr(&self, n: isize) -> P<ast::Expr> {
+        let idx = &self.idx;
+        let array = &self.array;
+        let cells = if self.opts.dialect.bits() {
+            quote_expr!(self.cx, $array.len() * 8)
+        } else {
+            quote_expr!(self.cx, $array.len())
+        };
+        if self.opts.dialect == Dialect::Smallfuck {
+            return if n < 0 {
+                let n = -n as usize;
+                quote_expr!(self.cx, {
+                    if $idx >= $n {
+                        $idx -= $n;
+                    } else {
+                        return Ok(_array);
+                    }
+                })
+            } else {
+                let n = n as usize;
+                quote_expr!(self.cx, {
+                    if $idx + $n < $cells {
+                        $idx += $n;
+                    } else {
+                        return Ok(_array);
+                    }
+                })
+            }
+        }
+        if n < 0 {
+            let n = -n as usize;
+            quote_expr!(self.cx, {
+                if $idx > $n {
+                    $idx -= $n;
+                } else {
+                    $idx = 0;
+                }
+            })
+        } else {
+            let n = n as usize;
+            quote_expr!(self.cx, {
+                if $idx + $n < $cells {
+                    $idx += $n;
+                } else {
+                    $idx = $cells - 1;
+                }
+            })
+        }
+    }
+
+    // `+` and `-`, possibly several at once
+    fn add_expr(&self, n: i32) -> P<ast::Expr> {
+        let idx = &self.idx;
+        let array = &self.array;
+        let n = n as u8;
+        quote_expr!(self.cx, {
+            $array[$idx] += $n
+        })
+    }
+
+    // `.`
+    fn output_expr(&self) -> P<ast::Expr> {
+        let idx = &self.idx;
+        let array = &self.array;
+        let wtr = &self.wtr;
+        quote_expr!(self.cx, try!($wtr.write(&[$array[$idx]])))
+    }
+
+    // `,`
+    fn input_expr(&self) -> P<ast::Expr> {
+        let idx = &self.idx;
+        let array = &self.array;
+        let rdr = &self.rdr;
+        quote_expr!(self.cx, {
+            use std::old_io as io;
+            $array[$idx] = match $rdr.read_byte() {
+                Ok(b) => b,
+                Err(io::IoError { kind: io::EndOfFile, .. }) => -1,
+                Err(e) => return Err(e)
+            }
+        })
+    }
+
+    // Boolfuck's `;`: collect the current bit, writing a byte once
+    // there are eight, least significant first.
+    fn output_bit_expr(&self) -> P<ast::Expr> {
+        let idx = &self.idx;
+        let array = &self.array;
+        let wtr = &self.wtr;
+        quote_expr!(self.cx, {
+            _out_byte |= (($array[$idx / 8] >> ($idx % 8)) & 1) << _out_bits;
+            _out_bits += 1;
+            if _out_bits == 8 {
+                try!($wtr.write(&[_out_byte]));
+                _out_byte = 0;
+                _out_bits = 0;
+            }
+        })
+    }
+
+    // Boolfuck's `,`: read the next bit, least significant first,
+    // with the end of the input reading as zeros.
+    fn input_bit_expr(&self) -> P<ast::Expr> {
+        let idx = &self.idx;
+        let array = &self.array;
+        let rdr = &self.rdr;
+        quote_expr!(self.cx, {
+            use std::old_io as io;
+            if _in_bits == 0 {
+                _in_byte = match $rdr.read_byte() {
+                    Ok(b) => b,
+                    Err(io::IoError { kind: io::EndOfFile, .. }) => 0,
+                    Err(e) => return Err(e)
+                };
+                _in_bits = 8;
+            }
+            let _mask = 1 << ($idx % 8);
+            if _in_byte & 1 == 1 {
+                $array[$idx / 8] |= _mask;
+            } else {
+                $array[$idx / 8] &= !_mask;
+            }
+            _in_byte >>= 1;
+            _in_bits -= 1;
+        })
+    }
+
+    // pbrain's `(...)`: generate a function for the procedure, and
+    // record it in the table under the current cell. Procedures are
+    // numbered from 1, since 0 means undefined.
+    fn proc_expr(&self, sp: codemap::Span, body: &[Op]) -> P<ast::Expr> {
+        let n = self.num_procs.get() + 1;
+        self.num_procs.set(n);
+
+        let name = proc_name(n);
+        let body = self.ops_to_expr(sp, body);
+        let item = quote_item!(self.cx,
+            fn $name(_array: &mut [u8], _i: &mut usize, _procs: &mut [usize; 256],
+                     _r: &mut Reader, _w: &mut Writer) -> ::std::old_io::IoResult<()> {
+                $body;
+                Ok(())
+            }
+        ).unwrap();
+        self.procs.borrow_mut().push(item);
+
+        let idx = &self.idx;
+        let array = &self.array;
+        quote_expr!(self.cx, _procs[$array[$idx] as usize] = $n)
+    }
+
+    // `#`: dump the pointer and the cells around it, along with
+    // where the `#` is.
+    fn debug_expr(&self, sp: codemap::Span) -> P<ast::Expr> {
+        let loc = self.cx.codemap().lookup_char_pos(sp.lo);
+        let loc = format!("{}:{}:{}", loc.file.name, loc.line, loc.col.0 + 1);
+        let loc = &*loc;
+
+        let idx = &self.idx;
+        let array = &self.array;
+        let write = match self.opts.debug {
+            Some(Sink::Output) => {
+                let wtr = &self.wtr;
+                quote_expr!(self.cx, try!($wtr.write_str(&*_dump)))
+            }
+            Some(Sink::Stdout) => {
+                quote_expr!(self.cx, { let _ = ::std::old_io::stdio::stdout().write_str(&*_dump); })
+            }
+            Some(Sink::Stderr) | None => {
+                quote_expr!(self.cx, { let _ = ::std::old_io::stdio::stderr().write_str(&*_dump); })
+            }
+        };
+        let (cells, cell) = if self.opts.dialect.bits() {
+            (quote_expr!(self.cx, $array.len() * 8),
+             quote_expr!(self.cx, ($array[j / 8] >> (j % 8)) & 1))
+        } else {
+            (quote_expr!(self.cx, $array.len()), quote_expr!(self.cx, $array[j]))
+        };
+        quote_expr!(self.cx, {
+            let _lo = if $idx > 8 { $idx - 8 } else { 0 };
+            let _hi = ::std::cmp::min($idx + 8, $cells - 1);
+            let _cells = (_lo.._hi + 1).map(|j| {
+                if j == $idx { format!("[{}]", $cell) } else { format!("{}", $cell) }
+            }).collect::<Vec<String>>();
+            let _dump = format!("{}: pointer {}, cells {}..{}: {}\n",
+                                $loc, $idx, _lo, _hi, _cells.connect(" "));
+            $write
+        })
+    }
+
+    // `[>]`, `[<]` and other loops that only move
+    fn scan_expr(&self, n: isize) -> P<ast::Expr> {
+        let idx = &self.idx;
+        let array = &self.array;
+        // if there's no zero cell to find, the pointer gets stuck at
+        // the end of the tape, on a non-zero cell, forever.
+        match n {
+            1 if !self.opts.dialect.bits() => quote_expr!(self.cx, {
+                match $array[$idx..].iter().position(|&c| c == 0) {
+                    Some(j) => $idx += j,
+                    None => loop {}
+                }
+            }),
+            -1 if !self.opts.dialect.bits() => quote_expr!(self.cx, {
+                match $array[..$idx + 1].iter().rposition(|&c| c == 0) {
+                    Some(j) => $idx = j,
+                    None => loop {}
+                }
+            }),
+            _ => self.loop_expr(self.move_expr(n))
+        }
+    }
+
+    // multiplication loops like `[->+>++<<]`, which just add the
+    // current cell times each factor to the cell at each offset,
+    // unless that would go off the ends of the tape, in which case
+    // the original loop is run so that the pointer is clamped the
+    // same way.
+    fn mul_expr(&self, sp: codemap::Span, targets: &[(isize, i32)], body: &[Op]) -> P<ast::Expr> {
+        let idx = &self.idx;
+        let array = &self.array;
+
+        let adds = targets.iter().map(|&(off, n)| {
+            let n = n as u8;
+            let target = if off < 0 {
+                let off = -off as usize;
+                quote_expr!(self.cx, $idx - $off)
+            } else {
+                let off = off as usize;
+                quote_expr!(self.cx, $idx + $off)
+            };
+            self.cx.stmt_expr(quote_expr!(self.cx, $array[$target] += _c * $n))
+        }).collect();
+        let adds = self.cx.expr_block(self.cx.block(sp, adds, None));
+
+        let (lo, hi) = opt::visited(body);
+        let (lo, hi) = (-lo as usize, hi as usize);
+        let in_bounds = if lo > 0 {
+            quote_expr!(self.cx, $idx >= $lo && $idx + $hi < $array.len())
+        } else {
+            quote_expr!(self.cx, $idx + $hi < $array.len())
+        };
+        let slow = self.loop_expr(self.ops_to_expr(sp, body));
+
+        quote_expr!(self.cx, {
+            if $array[$idx] != 0 {
+                if $in_bounds {
+                    let _c = $array[$idx];
+                    $adds;
+                    $array[$idx] = 0;
+                } else {
+                    $slow
+                }
+            }
+        })
+    }
+
+    // `[...]`, given the translation of the `...`
+    fn loop_expr(&self, centre: P<ast::Expr>) -> P<ast::Expr> {
+        let idx = &self.idx;
+        let array = &self.array;
+        if self.opts.dialect.bits() {
+            return quote_expr!(self.cx, {
+                while ($array[$idx / 8] >> ($idx % 8)) & 1 != 0 {
+                    $centre
+                }
+            })
+        }
+        quote_expr!(self.cx, {
+            while $array[$idx] != 0 {
+                $centre
+            }
+        })
+    }
+}
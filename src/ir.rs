@@ -0,0 +1,15 @@
+//! The commands of a brainfuck program as a tree (from `bf_ir`), so
+//! that it can be analysed before generating code, and conversions
+//! between its positions and spans.
+
+use syntax::codemap::{self, Span, BytePos};
+
+pub use bf_ir::{Op, Pos};
+
+pub fn pos(sp: Span) -> Pos {
+    Pos { lo: sp.lo.0 as usize, hi: sp.hi.0 as usize }
+}
+
+pub fn span(pos: Pos) -> Span {
+    codemap::mk_sp(BytePos(pos.lo as u32), BytePos(pos.hi as u32))
+}
@@ -7,20 +7,29 @@
 
 extern crate syntax;
 extern crate rustc;
+extern crate bf_ir;
 
 use syntax::ast;
-use syntax::ptr::P;
 use syntax::codemap;
 use syntax::ext::base::{ExtCtxt, MacResult, MacExpr};
-use syntax::ext::build::AstBuilder;
-use syntax::parse::token;
+use syntax::ptr::P;
 
 use rustc::plugin::Registry;
 
+use bf_ir::{analysis, opt};
+
+mod alphabet;
+mod codegen;
+mod ir;
+mod ook;
+mod options;
+mod parser;
+
 #[plugin_registrar]
 #[doc(hidden)]
 pub fn plugin_registrar(registrar: &mut Registry) {
-    registrar.register_macro("brainfuck", brainfuck)
+    registrar.register_macro("brainfuck", brainfuck);
+    registrar.register_macro("ook", ook)
 }
 
 
@@ -28,164 +37,63 @@ pub fn plugin_registrar(registrar: &mut Registry) {
 // given in the table at:
 // http://en.wikipedia.org/wiki/Brainfuck#Commands
 fn brainfuck(cx: &mut ExtCtxt, sp: codemap::Span, tts: &[ast::TokenTree]) -> Box<MacResult+'static> {
-    let bf = BF {
-        array: quote_expr!(&mut *cx, _array),
-        idx: quote_expr!(&mut *cx, _i),
-        rdr: quote_expr!(&mut *cx, _r),
-        wtr: quote_expr!(&mut *cx, _w),
-        cx: cx,
-    };
-    let core_code = bf.tts_to_expr(sp, tts);
+    let (opts, tts) = options::parse(cx, tts);
+    let (ops, input) = parser::Parser { cx: cx, opts: &opts }.parse(tts);
+    expand(cx, sp, &opts, ops, input)
+}
 
-    MacExpr::new(quote_expr!(bf.cx, {
-        fn run(_r: &mut Reader, _w: &mut Writer) -> ::std::old_io::IoResult<Vec<u8>> {
-            let mut _array = vec![0u8; 30_000];
-            let mut _i = 0;
-            $core_code;
-            Ok(_array)
+/// Expand `brainfuck!(tts)` outside of the compiler, returning the
+/// function it expands to. Errors are reported through `cx`, so the
+/// caller has to check for them. This is for `bf2rs` and the tests of
+/// the diagnostics.
+#[doc(hidden)]
+pub fn expand_brainfuck(cx: &mut ExtCtxt, sp: codemap::Span,
+                        tts: &[ast::TokenTree]) -> P<ast::Expr> {
+    // quoting needs to know where the macro was called
+    cx.bt_push(codemap::ExpnInfo {
+        call_site: sp,
+        callee: codemap::NameAndSpan {
+            name: "brainfuck".to_string(),
+            format: codemap::MacroBang,
+            span: None
         }
-        run
-    }))
+    });
+    let expr = brainfuck(cx, sp, tts).make_expr();
+    cx.bt_pop();
+    expr.expect("brainfuck: expansion wasn't an expression")
 }
 
-struct BF<'a> {
-    cx: &'a ExtCtxt<'a>,
-    array: P<ast::Expr>,
-    idx: P<ast::Expr>,
-    rdr: P<ast::Expr>,
-    wtr: P<ast::Expr>
+fn ook(cx: &mut ExtCtxt, sp: codemap::Span, tts: &[ast::TokenTree]) -> Box<MacResult+'static> {
+    let (opts, tts) = options::parse(cx, tts);
+    let ops = ook::parse(cx, tts);
+    expand(cx, sp, &opts, ops, None)
 }
 
-impl<'a> BF<'a> {
-    fn tts_to_expr(&self, sp: codemap::Span, tts: &[ast::TokenTree]) -> P<ast::Expr> {
-        let v = tts.iter()
-            .filter_map(|tt| self.tt_to_expr(sp,tt).map(|e| self.cx.stmt_expr(e)))
-            .collect();
-
-        let block = self.cx.block(sp, v, None);
-        self.cx.expr_block(block)
+/// Analyse and optimise `ops`, and generate the function that runs
+/// them, reading `input` (if any) before the real input.
+fn expand(cx: &mut ExtCtxt, sp: codemap::Span, opts: &options::Options,
+          ops: Vec<ir::Op>, input: Option<Vec<u8>>) -> Box<MacResult+'static> {
+    let threaded = opts.dialect == options::Dialect::Brainfork;
+    let passes = opts.passes();
+    let (ops, report) = analysis::analyse(ops, threaded, false);
+    for &loop_pos in report.infinite.iter() {
+        cx.span_warn(ir::span(loop_pos), "this loop never terminates once entered");
     }
-
-    fn tt_to_expr(&self, _sp: codemap::Span, tt: &ast::TokenTree) -> Option<P<ast::Expr>> {
-        match *tt {
-            ast::TtToken(sp, ref tok) => self.token_to_expr(sp, tok),
-
-            // [...] or (...) or {...}
-            ast::TtDelimited(sp, ref toks) => {
-                if toks.delim == token::Bracket {
-                    // [...]
-                    let centre = self.tts_to_expr(sp, &*toks.tts);
-
-                    let array = &self.array;
-                    let idx = &self.idx;
-
-                    Some(quote_expr!(self.cx, {
-                        while $array[$idx] != 0 {
-                            $centre
-                        }
-                    }))
-                } else {
-                    // not [...], so just translate directly (the
-                    // delimiters are definitely invalid, so just
-                    // ignoring them is fine)
-                    Some(self.tts_to_expr(sp,toks.tts.as_slice()))
-                }
-            }
-            ast::TtSequence(sp, _) => {
-                self.cx.span_err(sp, "sequences unsupported in `brainfuck!`");
-                None
-            }
+    if opts.report {
+        cx.span_note(sp, &*format!("brainfuck: {} loops, {} never terminate, {} never entered",
+                                   report.loops, report.infinite.len(), report.dead.len()));
+        let msg = if passes.contains(&opt::Pass::DeadLoops) {
+            "this loop is never entered, and was removed"
+        } else {
+            "this loop is never entered"
+        };
+        for &loop_pos in report.dead.iter() {
+            cx.span_note(ir::span(loop_pos), msg);
         }
     }
 
-    fn token_to_expr(&self, sp: codemap::Span,
-                     tok: &token::Token) -> Option<P<ast::Expr>> {
-        // some tokens consist of multiple characters that brainfuck
-        // needs to know about, so we do the obvious thing of just
-        // taking each one and combining into a single expression.
-        macro_rules! recompose {
-            ($($token: expr),*) => {
-                {
-                    let stmts = vec!(
-                        $(
-                            {
-                                let e = self.token_to_expr(sp,&$token)
-                                    .expect("brainfuck: invalid token decomposition?");
-                                self.cx.stmt_expr(e)
-                            } ),* );
-                    Some(self.cx.expr_block(self.cx.block(sp, stmts, None)))
-                }
-            }
-        }
-        let idx = &self.idx;
-        let array = &self.array;
-        match *tok {
-            token::Lt | token::Gt => {
-                let left = *tok == token::Lt;
-                Some(quote_expr!(self.cx, {
-                    if $left {
-                        if $idx > 0 {
-                            $idx -= 1;
-                        }
-                    } else {
-                        if $idx < $array.len() - 1 {
-                            $idx += 1;
-                        }
-                    }
-                }))
-            }
-
-            // = does nothing, so just ignore it in += >>= etc.
-            token::BinOpEq(a) => recompose!(token::BinOp(a)),
-            // <<
-            token::BinOp(token::Shl) => {
-                recompose!(token::Lt, token::Lt)
-            }
-            // >>
-            token::BinOp(token::Shr) => {
-                recompose!(token::Gt, token::Gt)
-            }
-
-            token::Dot => {
-                let wtr = &self.wtr;
-                Some(quote_expr!(self.cx, try!($wtr.write(&[$array[$idx]]))))
-            }
-            // ..
-            token::DotDot => recompose!(token::Dot, token::Dot),
-            // ...
-            token::DotDotDot => recompose!(token::Dot, token::Dot, token::Dot),
-
-
-            token::Comma => {
-                let rdr = &self.rdr;
-                Some(quote_expr!(self.cx, {
-                    use std::old_io as io;
-                    $array[$idx] = match $rdr.read_byte() {
-                        Ok(b) => b,
-                        Err(io::IoError { kind: io::EndOfFile, .. }) => -1,
-                        Err(e) => return Err(e)
-                    }
-                }))
-            }
+    let ops = opt::optimise(ops, &*passes, threaded);
 
-
-            token::BinOp(a @ token::Plus) | token::BinOp(a @ token::Minus) => {
-                let dir: u8 = if a == token::Plus { 1 } else { -1 };
-
-                Some(quote_expr!(self.cx, {
-                    $array[$idx] += $dir
-                }))
-            }
-            // =>
-            token::FatArrow => recompose!(token::Gt),
-            // ->
-            token::RArrow => recompose!(token::BinOp(token::Minus), token::Gt),
-            // <-
-            token::LArrow => recompose!(token::Lt, token::BinOp(token::Minus)),
-
-            _ => {
-                None
-            }
-        }
-    }
+    let bf = codegen::BF::new(cx, opts);
+    MacExpr::new(bf.program(sp, &*ops, input))
 }
@@ -0,0 +1,70 @@
+//! The [Ook!](http://www.dangermouse.net/esoteric/ook.html) dialect,
+//! where each brainfuck command is a pair of `Ook.`, `Ook?` and
+//! `Ook!`s.
+
+use syntax::ast;
+use syntax::codemap;
+use syntax::ext::base::ExtCtxt;
+use syntax::parse::token;
+
+use ir::Op;
+use parser::BlockBuilder;
+
+#[derive(Clone, Copy, PartialEq)]
+enum Ook {
+    Dot,
+    Question,
+    Bang,
+}
+
+pub fn parse(cx: &ExtCtxt, tts: &[ast::TokenTree]) -> Vec<Op> {
+    // each `Ook` and its punctuation, with their combined span.
+    let mut words = vec![];
+    let mut iter = tts.iter();
+    while let Some(tt) = iter.next() {
+        match *tt {
+            ast::TtToken(ook_sp, token::Ident(id, _)) if &*token::get_ident(id) == "Ook" => {
+                let word = match iter.next() {
+                    Some(&ast::TtToken(sp, ref tok)) => {
+                        let ook = match *tok {
+                            token::Dot => Some(Ook::Dot),
+                            token::Question => Some(Ook::Question),
+                            token::Not => Some(Ook::Bang),
+                            _ => None
+                        };
+                        ook.map(|ook| (codemap::mk_sp(ook_sp.lo, sp.hi), ook))
+                    }
+                    _ => None
+                };
+                match word {
+                    Some(word) => words.push(word),
+                    None => cx.span_err(ook_sp, "expected `.`, `?` or `!` after `Ook`")
+                }
+            }
+            _ => cx.span_err(tt.get_span(), "expected `Ook.`, `Ook?` or `Ook!`")
+        }
+    }
+
+    if words.len() % 2 == 1 {
+        cx.span_err(words[words.len() - 1].0, "unpaired Ook! word");
+    }
+
+    let mut builder = BlockBuilder::new("`Ook! Ook?`", "`Ook? Ook!`");
+    for pair in words.chunks(2).filter(|pair| pair.len() == 2) {
+        let sp = codemap::mk_sp(pair[0].0.lo, pair[1].0.hi);
+        match (pair[0].1, pair[1].1) {
+            (Ook::Dot, Ook::Question) => builder.push(Op::Move(1)),
+            (Ook::Question, Ook::Dot) => builder.push(Op::Move(-1)),
+            (Ook::Dot, Ook::Dot) => builder.push(Op::Add(1)),
+            (Ook::Bang, Ook::Bang) => builder.push(Op::Add(-1)),
+            (Ook::Bang, Ook::Dot) => builder.push(Op::Out),
+            (Ook::Dot, Ook::Bang) => builder.push(Op::In),
+            (Ook::Bang, Ook::Question) => builder.open(sp),
+            (Ook::Question, Ook::Bang) => builder.close(cx, sp),
+            (Ook::Question, Ook::Question) => {
+                cx.span_err(sp, "`Ook? Ook?` is not an Ook! command")
+            }
+        }
+    }
+    builder.finish(cx)
+}
@@ -0,0 +1,323 @@
+//! Options that can be given before the program, like
+//! `brainfuck!(fidelity; ...)`.
+
+use syntax::ast;
+use syntax::codemap;
+use syntax::ext::base::ExtCtxt;
+use syntax::parse::token;
+
+use bf_ir::opt::{self, Pass};
+
+pub use bf_ir::Dialect;
+
+use alphabet::{self, Alphabet};
+
+/// The names that can start an option list. A program that doesn't
+/// start with one of these is treated as brainfuck all the way
+/// through.
+static NAMES: &'static [&'static str] = &[
+    "fidelity",
+    "warnings",
+    "strict",
+    "prose",
+    "report",
+    "dialect",
+    "debug",
+    "alphabet",
+    "opt",
+    "passes",
+];
+
+/// The optimisation level without an `opt` option.
+const DEFAULT_OPT: u32 = 1;
+
+/// How to report something that might be a mistake.
+#[derive(Clone, Copy, PartialEq)]
+pub enum Level {
+    Allow,
+    Warn,
+    Deny,
+}
+
+impl Default for Level {
+    fn default() -> Level { Level::Warn }
+}
+
+/// Where the `#` debug dumps are written.
+#[derive(Clone, Copy, PartialEq)]
+pub enum Sink {
+    Stderr,
+    Stdout,
+    /// The same `Writer` as the program's output.
+    Output,
+}
+
+#[derive(Default)]
+pub struct Options {
+    /// Interpret every character of the source of each token,
+    /// rather than the fixed decomposition of known tokens.
+    pub fidelity: bool,
+    /// Warn about input that is silently ignored or flattened.
+    pub warnings: bool,
+    /// Like `warnings`, but errors.
+    pub strict: bool,
+    /// Commands that look like they're part of prose, like the `.`
+    /// at the end of a sentence.
+    pub prose: Level,
+    /// Describe the results of analysing the program as notes.
+    pub report: bool,
+    pub dialect: Dialect,
+    /// Make `#` dump the tape around the pointer.
+    pub debug: Option<Sink>,
+    /// Words for the brainfuck commands, instead of the usual tokens.
+    pub alphabet: Option<Alphabet>,
+    /// The optimisation level, from 0 to 3.
+    pub opt: Option<u32>,
+    /// Passes turned on or off by name, regardless of the level.
+    pub passes: Vec<(Pass, bool)>,
+}
+
+impl Options {
+    /// The optimisation passes to run.
+    pub fn passes(&self) -> Vec<Pass> {
+        let mut passes = opt::passes(self.opt.unwrap_or(DEFAULT_OPT));
+        for &(pass, on) in self.passes.iter() {
+            passes.retain(|&p| p != pass);
+            if on {
+                passes.push(pass)
+            }
+        }
+        passes
+    }
+
+    fn set(&mut self, cx: &ExtCtxt, sp: codemap::Span, name: &str,
+           value: Option<&ast::TokenTree>) {
+        match name {
+            "fidelity" => {
+                no_value(cx, name, value);
+                self.fidelity = true
+            }
+            "warnings" => {
+                no_value(cx, name, value);
+                self.warnings = true
+            }
+            "strict" => {
+                no_value(cx, name, value);
+                self.strict = true
+            }
+            "prose" => self.prose = level(cx, sp, name, value),
+            "report" => {
+                no_value(cx, name, value);
+                self.report = true
+            }
+            "dialect" => self.dialect = dialect(cx, sp, value),
+            "debug" => self.debug = Some(sink(cx, value)),
+            "alphabet" => self.alphabet = alphabet::parse(cx, sp, value),
+            "opt" => self.opt = Some(level_number(cx, sp, value)),
+            "passes" => passes(cx, sp, value, &mut self.passes),
+            _ => cx.span_err(sp, &*format!("unknown `brainfuck!` option `{}`", name))
+        }
+    }
+}
+
+fn no_value(cx: &ExtCtxt, name: &str, value: Option<&ast::TokenTree>) {
+    if let Some(tt) = value {
+        cx.span_err(tt.get_span(),
+                    &*format!("`brainfuck!` option `{}` doesn't take a value", name));
+    }
+}
+
+fn level(cx: &ExtCtxt, sp: codemap::Span, name: &str,
+         value: Option<&ast::TokenTree>) -> Level {
+    if let Some(&ast::TtToken(_, token::Ident(id, _))) = value {
+        match &*token::get_ident(id) {
+            "allow" => return Level::Allow,
+            "warn" => return Level::Warn,
+            "deny" => return Level::Deny,
+            _ => {}
+        }
+    }
+    let sp = value.map_or(sp, |tt| tt.get_span());
+    cx.span_err(sp, &*format!("`brainfuck!` option `{}` should be one of `allow`, `warn` or `deny`",
+                              name));
+    Level::Warn
+}
+
+fn dialect(cx: &ExtCtxt, sp: codemap::Span, value: Option<&ast::TokenTree>) -> Dialect {
+    if let Some(&ast::TtToken(_, token::Ident(id, _))) = value {
+        if let Some(dialect) = Dialect::from_name(&*token::get_ident(id)) {
+            return dialect
+        }
+    }
+    let sp = value.map_or(sp, |tt| tt.get_span());
+    cx.span_err(sp, "unknown `brainfuck!` dialect, expected one of `brainfuck`, `pbrain`, \
+                     `brainfork`, `ebf1`, `boolfuck` or `smallfuck`");
+    Dialect::Brainfuck
+}
+
+fn level_number(cx: &ExtCtxt, sp: codemap::Span, value: Option<&ast::TokenTree>) -> u32 {
+    if let Some(&ast::TtToken(_, token::Literal(token::Integer(name), None))) = value {
+        match token::get_name(name).parse() {
+            Ok(n) if n <= opt::MAX_LEVEL => return n,
+            _ => {}
+        }
+    }
+    let sp = value.map_or(sp, |tt| tt.get_span());
+    cx.span_err(sp, "`brainfuck!` option `opt` should be one of `0`, `1`, `2` or `3`");
+    DEFAULT_OPT
+}
+
+/// Parse `{ pass = on|off, ... }`.
+fn passes(cx: &ExtCtxt, sp: codemap::Span, value: Option<&ast::TokenTree>,
+          passes: &mut Vec<(Pass, bool)>) {
+    let tts = match value {
+        Some(&ast::TtDelimited(_, ref delimed)) if delimed.delim == token::Brace => &*delimed.tts,
+        _ => {
+            cx.span_err(value.map_or(sp, |tt| tt.get_span()),
+                        "`brainfuck!` option `passes` should be followed by \
+                         `{ pass = on|off, ... }`");
+            return
+        }
+    };
+
+    let entries = tts.split(|tt| match *tt {
+        ast::TtToken(_, token::Comma) => true,
+        _ => false
+    });
+    for entry in entries.filter(|entry| !entry.is_empty()) {
+        let pass = match entry[0] {
+            ast::TtToken(sp, token::Ident(id, _)) => {
+                let name = token::get_ident(id);
+                match Pass::from_name(&*name) {
+                    Some(pass) => pass,
+                    None => {
+                        let names: Vec<_> = opt::PASSES.iter()
+                            .map(|p| format!("`{}`", p.name()))
+                            .collect();
+                        cx.span_err(sp, &*format!("unknown optimisation pass `{}`, expected one \
+                                                   of {}", name, names.connect(", ")));
+                        continue
+                    }
+                }
+            }
+            ref tt => {
+                cx.span_err(tt.get_span(), "expected an optimisation pass name");
+                continue
+            }
+        };
+        let on = match (entry.get(1), entry.get(2), entry.len()) {
+            (Some(&ast::TtToken(_, token::Eq)), Some(&ast::TtToken(_, token::Ident(id, _))), 3) => {
+                match &*token::get_ident(id) {
+                    "on" => Some(true),
+                    "off" => Some(false),
+                    _ => None
+                }
+            }
+            _ => None
+        };
+        match on {
+            Some(on) => passes.push((pass, on)),
+            None => {
+                cx.span_err(entry[0].get_span(), "expected `= on` or `= off` after the pass name")
+            }
+        }
+    }
+}
+
+fn sink(cx: &ExtCtxt, value: Option<&ast::TokenTree>) -> Sink {
+    match value {
+        None => Sink::Stderr,
+        Some(&ast::TtToken(_, token::Ident(id, _))) if &*token::get_ident(id) == "stderr" => {
+            Sink::Stderr
+        }
+        Some(&ast::TtToken(_, token::Ident(id, _))) if &*token::get_ident(id) == "stdout" => {
+            Sink::Stdout
+        }
+        Some(&ast::TtToken(_, token::Ident(id, _))) if &*token::get_ident(id) == "output" => {
+            Sink::Output
+        }
+        Some(tt) => {
+            cx.span_err(tt.get_span(),
+                        "unknown `brainfuck!` debug sink, expected one of `stderr`, `stdout` \
+                         or `output`");
+            Sink::Stderr
+        }
+    }
+}
+
+fn option_name(tt: &ast::TokenTree) -> Option<(codemap::Span, token::InternedString)> {
+    match *tt {
+        ast::TtToken(sp, token::Ident(id, _)) => Some((sp, token::get_ident(id))),
+        _ => None
+    }
+}
+
+/// Parse the leading `name, name = value, name { ... };` list, if
+/// there is one (that is, the program starts with an option name and
+/// has a `;` outside any brackets), returning the options and the tokens of the program itself.
+pub fn parse<'b>(cx: &ExtCtxt, tts: &'b [ast::TokenTree]) -> (Options, &'b [ast::TokenTree]) {
+    let mut opts: Options = Default::default();
+
+    match tts.first().and_then(option_name) {
+        Some((_, ref name)) if NAMES.iter().any(|n| *n == &**name) => {}
+        _ => return (opts, tts)
+    }
+    // prose can start with one of the names too (like `debug this
+    // loop`), so they're only options if there's a `;` to end them
+    let has_semi = tts.iter().any(|tt| match *tt {
+        ast::TtToken(_, token::Semi) => true,
+        _ => false
+    });
+    if !has_semi {
+        return (opts, tts)
+    }
+
+    let mut i = 0;
+    loop {
+        let (sp, name) = match tts.get(i).and_then(option_name) {
+            Some(x) => x,
+            None => {
+                let sp = tts.get(i).unwrap_or(&tts[i - 1]).get_span();
+                cx.span_err(sp, "expected a `brainfuck!` option name");
+                return (opts, &[])
+            }
+        };
+        i += 1;
+
+        let value = match tts.get(i) {
+            // name = value
+            Some(&ast::TtToken(_, token::Eq)) => {
+                i += 2;
+                match tts.get(i - 1) {
+                    Some(tt) => Some(tt),
+                    None => {
+                        cx.span_err(tts[i - 2].get_span(),
+                                    "expected a value for `brainfuck!` option");
+                        return (opts, &[])
+                    }
+                }
+            }
+            // name { ... }
+            Some(tt @ &ast::TtDelimited(_, ref delimed)) if delimed.delim == token::Brace => {
+                i += 1;
+                Some(tt)
+            }
+            _ => None
+        };
+        opts.set(cx, sp, &*name, value);
+
+        match tts.get(i) {
+            Some(&ast::TtToken(_, token::Comma)) => i += 1,
+            Some(&ast::TtToken(_, token::Semi)) => return (opts, &tts[i + 1..]),
+            Some(tt) => {
+                cx.span_err(tt.get_span(), "expected `,` or `;` after `brainfuck!` option");
+                return (opts, &[])
+            }
+            None => {
+                cx.span_err(tts[i - 1].get_span(),
+                            "expected `;` between `brainfuck!` options and program");
+                return (opts, &[])
+            }
+        }
+    }
+}
@@ -0,0 +1,502 @@
+//! Converting the macro input into brainfuck commands.
+
+use std::mem;
+
+use syntax::ast;
+use syntax::codemap;
+use syntax::ext::base::ExtCtxt;
+use syntax::parse::{self, token};
+use syntax::print::pprust;
+
+use alphabet::{Alphabet, Command};
+use ir::{self, Op};
+use options::{self, Dialect};
+
+pub struct Parser<'a> {
+    pub cx: &'a ExtCtxt<'a>,
+    pub opts: &'a options::Options,
+}
+
+/// The characters that `tok` is made of, for those that might be
+/// commands in some dialect.
+///
+/// Some tokens consist of multiple characters that brainfuck needs to
+/// know about, so we do the obvious thing of just taking each one in
+/// turn. Others, like `=>` and `!=`, only include the characters that
+/// were historically interpreted.
+fn token_chars(tok: &token::Token) -> Option<&'static str> {
+    let s = match *tok {
+        token::Lt => "<",
+        token::Gt => ">",
+        // = does nothing, so just ignore it in += >>= etc.
+        token::BinOp(op) | token::BinOpEq(op) => match op {
+            token::Plus => "+",
+            token::Minus => "-",
+            token::Star => "*",
+            token::Shl => "<<",
+            token::Shr => ">>",
+            _ => return None
+        },
+        token::Dot => ".",
+        token::DotDot => "..",
+        token::DotDotDot => "...",
+        token::Comma => ",",
+        token::Semi => ";",
+        token::Colon => ":",
+        token::ModSep => "::",
+        token::At => "@",
+        token::Dollar => "$",
+        token::Not | token::Ne => "!",
+        token::Tilde => "~",
+        token::FatArrow => ">",
+        token::RArrow => "->",
+        token::LArrow => "<-",
+        _ => return None
+    };
+    Some(s)
+}
+
+/// Whether `token_to_ops` translates `tok` into anything.
+fn is_command(dialect: Dialect, tok: &token::Token) -> bool {
+    token_chars(tok).map_or(false, |s| s.chars().any(|c| dialect.command(c).is_some()))
+}
+
+/// If `tts` is a single string literal (raw or not), return it.
+fn single_str_lit(tts: &[ast::TokenTree]) -> Option<(codemap::Span, token::Lit)> {
+    if tts.len() != 1 { return None }
+    match tts[0] {
+        ast::TtToken(sp, token::Literal(lit @ token::Str_(_), _)) |
+        ast::TtToken(sp, token::Literal(lit @ token::StrRaw(..), _)) => Some((sp, lit)),
+        _ => None
+    }
+}
+
+/// The contents of a string literal, along with the offset of the
+/// first character from the start of the literal, if the characters
+/// line up exactly with the source.
+fn str_lit_contents(lit: token::Lit) -> (String, Option<usize>) {
+    match lit {
+        token::Str_(name) => {
+            let source = token::get_name(name);
+            let s = parse::str_lit(&*source);
+            // escapes mean the characters don't line up with the
+            // source.
+            let offset = if s == &*source { Some(1) } else { None };
+            (s, offset)
+        }
+        // r##"..."##
+        token::StrRaw(name, hashes) => {
+            (parse::raw_str_lit(&*token::get_name(name)), Some(2 + hashes))
+        }
+        _ => panic!("brainfuck: non-string literal in `str_lit_contents`")
+    }
+}
+
+impl<'a> Parser<'a> {
+    /// Parse the whole program (after any options), returning it
+    /// along with any input embedded after a `!`.
+    pub fn parse(&self, tts: &[ast::TokenTree]) -> (Vec<Op>, Option<Vec<u8>>) {
+        let mut ops = vec![];
+        // brainfuck!("...") or brainfuck!(r#"..."#)
+        if let (None, Some((sp, lit))) = (self.opts.alphabet.as_ref(), single_str_lit(tts)) {
+            let input = self.str_lit_to_ops(sp, lit, &mut ops);
+            return (ops, input)
+        }
+
+        // code ! data, unless `!` is part of a word
+        let separator = self.opts.dialect.input_separator() &&
+            self.opts.alphabet.as_ref().map_or(true, |a| !a.contains(&token::Not));
+        let (code, input) = match tts.iter().position(|tt| match *tt {
+            ast::TtToken(_, token::Not) => separator,
+            _ => false
+        }) {
+            Some(i) => (&tts[..i], Some(self.embedded_input(tts[i].get_span(), &tts[i + 1..]))),
+            None => (tts, None)
+        };
+        match self.opts.alphabet {
+            Some(ref alphabet) => self.words_to_ops(alphabet, code, &mut ops),
+            None => {
+                // with `fidelity`, a loop can start in one token and
+                // end in another, so they're all matched up together
+                let mut builder = BlockBuilder::new("`[`", "`]`");
+                self.tts_to_ops(code, &mut builder);
+                ops = builder.finish(self.cx)
+            }
+        }
+        (ops, input)
+    }
+
+    /// The input after the `!` at `sp`: either a single string
+    /// literal, or the source text of the tokens.
+    fn embedded_input(&self, sp: codemap::Span, tts: &[ast::TokenTree]) -> Vec<u8> {
+        if let Some((_, lit)) = single_str_lit(tts) {
+            return str_lit_contents(lit).0.into_bytes()
+        }
+        let (first, last) = match (tts.first(), tts.last()) {
+            (Some(first), Some(last)) => (first.get_span(), last.get_span()),
+            _ => return vec![]
+        };
+        match self.cx.codemap().span_to_snippet(codemap::mk_sp(first.lo, last.hi)) {
+            Some(s) => s.into_bytes(),
+            None => {
+                self.cx.span_err(sp, "can't find the source of the input after `!`, \
+                                      try using a string literal");
+                vec![]
+            }
+        }
+    }
+
+    fn tts_to_ops(&self, tts: &[ast::TokenTree], builder: &mut BlockBuilder) {
+        self.check_prose(tts);
+        for tt in tts.iter() {
+            self.tt_to_ops(tt, builder)
+        }
+    }
+
+    fn tt_to_ops(&self, tt: &ast::TokenTree, builder: &mut BlockBuilder) {
+        match *tt {
+            ast::TtToken(sp, ref tok) => {
+                if self.opts.fidelity {
+                    // the tokens from a macro expansion might not
+                    // have any source to look at.
+                    if let Some(source) = self.cx.codemap().span_to_snippet(sp) {
+                        return self.chars_to_ops(sp, &*source, Some(sp.lo), builder)
+                    }
+                }
+                self.token_to_ops(sp, tok, builder);
+            }
+
+            // [...] or (...) or {...}
+            ast::TtDelimited(_, ref toks) => {
+                if toks.delim == token::Bracket {
+                    // [...]
+                    builder.open(toks.open_span);
+                    self.tts_to_ops(&*toks.tts, builder);
+                    builder.close(self.cx, toks.close_span)
+                } else if toks.delim == token::Paren && self.opts.dialect == Dialect::Pbrain {
+                    // (...) defines a procedure
+                    builder.open_proc(toks.open_span);
+                    self.tts_to_ops(&*toks.tts, builder);
+                    builder.close_proc(self.cx, toks.close_span)
+                } else if toks.delim == token::Brace && self.opts.dialect == Dialect::Ebf1 {
+                    // {...} is a left shift, the contents, and then
+                    // a right shift
+                    builder.push(Op::ShiftLeft);
+                    self.tts_to_ops(&*toks.tts, builder);
+                    builder.push(Op::ShiftRight)
+                } else {
+                    // not [...], so just translate directly (the
+                    // delimiters are definitely invalid, so just
+                    // ignoring them is fine)
+                    let (open, close) = if toks.delim == token::Paren { ("(", ")") } else { ("{", "}") };
+                    self.ignored(toks.open_span,
+                                 &*format!("`{}`...`{}` delimiters ignored by `brainfuck!`",
+                                           open, close));
+                    self.tts_to_ops(toks.tts.as_slice(), builder)
+                }
+            }
+            ast::TtSequence(sp, _) => {
+                self.cx.span_err(sp, "sequences unsupported in `brainfuck!`");
+            }
+        }
+    }
+
+    /// Translate `tts` using the words of `alphabet`, matching up the
+    /// words for loops like `chars_to_ops` does, since they needn't
+    /// be brackets.
+    fn words_to_ops(&self, alphabet: &Alphabet, tts: &[ast::TokenTree], ops: &mut Vec<Op>) {
+        if self.opts.dialect != Dialect::Brainfuck {
+            self.cx.span_err(alphabet.sp,
+                             "`alphabet` can only be used with the `brainfuck` dialect");
+            return
+        }
+
+        let mut toks = vec![];
+        self.flatten(tts, &mut toks);
+
+        let mut builder = BlockBuilder::new("`open` word", "`close` word");
+        let mut i = 0;
+        while i < toks.len() {
+            let (sp, ref tok) = toks[i];
+            let (len, command) = match alphabet.find(&toks[i..]) {
+                Some(x) => x,
+                None => {
+                    match *tok {
+                        token::Pound if self.opts.debug.is_some() => {
+                            builder.push(Op::Debug(ir::pos(sp)))
+                        }
+                        _ => {
+                            self.ignored(sp, &*format!("`{}` isn't in the `brainfuck!` alphabet",
+                                                       pprust::token_to_string(tok)))
+                        }
+                    }
+                    i += 1;
+                    continue
+                }
+            };
+            let sp = codemap::mk_sp(sp.lo, toks[i + len - 1].0.hi);
+            match command {
+                Command::Open => builder.open(sp),
+                Command::Close => builder.close(self.cx, sp),
+                command => builder.push(command.op().unwrap())
+            }
+            i += len;
+        }
+        ops.extend(builder.finish(self.cx).into_iter())
+    }
+
+    /// The tokens of `tts`, including delimiters.
+    fn flatten(&self, tts: &[ast::TokenTree], toks: &mut Vec<(codemap::Span, token::Token)>) {
+        for tt in tts.iter() {
+            match *tt {
+                ast::TtToken(sp, ref tok) => toks.push((sp, tok.clone())),
+                ast::TtDelimited(_, ref delimed) => {
+                    toks.push((delimed.open_span, token::OpenDelim(delimed.delim)));
+                    self.flatten(&*delimed.tts, toks);
+                    toks.push((delimed.close_span, token::CloseDelim(delimed.delim)));
+                }
+                ast::TtSequence(sp, _) => {
+                    self.cx.span_err(sp, "sequences unsupported in `brainfuck!`");
+                }
+            }
+        }
+    }
+
+    /// Look for commands that are glued onto words, like the `.` at
+    /// the end of a sentence or the `.`s and `-`s in a URL, since
+    /// they're probably prose that wasn't meant to be run.
+    fn check_prose(&self, tts: &[ast::TokenTree]) {
+        if self.opts.prose == options::Level::Allow { return }
+
+        fn word_span(tt: &ast::TokenTree) -> Option<codemap::Span> {
+            match *tt {
+                ast::TtToken(sp, token::Ident(..)) |
+                ast::TtToken(sp, token::Literal(..)) |
+                ast::TtToken(sp, token::Lifetime(..)) => Some(sp),
+                _ => None
+            }
+        }
+
+        for (i, tt) in tts.iter().enumerate() {
+            let sp = match *tt {
+                ast::TtToken(sp, ref tok) if is_command(self.opts.dialect, tok) => sp,
+                _ => continue
+            };
+            let after_word = i > 0 && word_span(&tts[i - 1]).map_or(false, |w| w.hi == sp.lo);
+            let before_word = tts.get(i + 1).and_then(word_span).map_or(false, |w| w.lo == sp.hi);
+            if !(after_word || before_word) { continue }
+
+            let msg = "brainfuck command attached to a word looks like prose, but will be run";
+            if self.opts.prose == options::Level::Deny {
+                self.cx.span_err(sp, msg)
+            } else {
+                self.cx.span_warn(sp, msg)
+            }
+            self.cx.span_help(sp, "move the text into a Rust comment (`//` or `/* ... */`)");
+        }
+    }
+
+    /// Translate the contents of a string literal character by
+    /// character, returning the input after the first `!`, if any.
+    fn str_lit_to_ops(&self, sp: codemap::Span, lit: token::Lit,
+                      ops: &mut Vec<Op>) -> Option<Vec<u8>> {
+        let (s, offset) = str_lit_contents(lit);
+        // without exact positions, only point at the whole literal.
+        let start = offset.map(|off| sp.lo + codemap::BytePos(off as u32));
+        let mut builder = BlockBuilder::new("`[`", "`]`");
+        let input = match s.find('!') {
+            Some(i) if self.opts.dialect.input_separator() => {
+                self.chars_to_ops(sp, &s[..i], start, &mut builder);
+                Some(s[i + 1..].as_bytes().to_vec())
+            }
+            _ => {
+                self.chars_to_ops(sp, &*s, start, &mut builder);
+                None
+            }
+        };
+        ops.extend(builder.finish(self.cx).into_iter());
+        input
+    }
+
+    /// Translate `s` character by character. Unlike token trees,
+    /// nothing guarantees that the `[`s and `]`s are balanced, so
+    /// they're matched up by `builder`.
+    ///
+    /// `start` is the position of the first character of `s`, if
+    /// the characters line up exactly with the source in `sp`.
+    fn chars_to_ops(&self, sp: codemap::Span, s: &str,
+                    start: Option<codemap::BytePos>, builder: &mut BlockBuilder) {
+        let char_sp = |i: usize, c: char| {
+            match start {
+                Some(start) => {
+                    let lo = start + codemap::BytePos(i as u32);
+                    codemap::mk_sp(lo, lo + codemap::BytePos(c.len_utf8() as u32))
+                }
+                None => sp
+            }
+        };
+
+        let pbrain = self.opts.dialect == Dialect::Pbrain;
+        for (i, c) in s.char_indices() {
+            match c {
+                '[' => builder.open(char_sp(i, c)),
+                ']' => builder.close(self.cx, char_sp(i, c)),
+                '(' if pbrain => builder.open_proc(char_sp(i, c)),
+                ')' if pbrain => builder.close_proc(self.cx, char_sp(i, c)),
+                '#' if self.opts.debug.is_some() => {
+                    builder.push(Op::Debug(ir::pos(char_sp(i, c))))
+                }
+                _ => {
+                    if let Some(op) = self.opts.dialect.command(c) {
+                        builder.push(op)
+                    }
+                }
+            }
+        }
+    }
+
+    fn token_to_ops(&self, sp: codemap::Span, tok: &token::Token, builder: &mut BlockBuilder) {
+        match *tok {
+            token::Pound if self.opts.debug.is_some() => builder.push(Op::Debug(ir::pos(sp))),
+
+            token::Literal(..) | token::Lifetime(..) => {
+                self.hidden_commands(sp);
+            }
+
+            // Y, YY, ...
+            token::Ident(id, _) if self.opts.dialect == Dialect::Brainfork => {
+                let name = token::get_ident(id);
+                if name.chars().all(|c| c == 'Y') {
+                    for _ in name.chars() {
+                        builder.push(Op::Fork)
+                    }
+                }
+            }
+
+            _ => {
+                if let Some(chars) = token_chars(tok) {
+                    for c in chars.chars() {
+                        if let Some(op) = self.opts.dialect.command(c) {
+                            builder.push(op)
+                        }
+                    }
+                }
+            }
+        }
+    }
+
+    /// Report input that was ignored, if asked to.
+    fn ignored(&self, sp: codemap::Span, msg: &str) {
+        if self.opts.strict {
+            self.cx.span_err(sp, msg)
+        } else if self.opts.warnings {
+            self.cx.span_warn(sp, msg)
+        }
+    }
+
+    /// Report any brainfuck commands in the source of the (ignored)
+    /// literal or lifetime at `sp`.
+    fn hidden_commands(&self, sp: codemap::Span) {
+        if !(self.opts.strict || self.opts.warnings) { return }
+        let source = match self.cx.codemap().span_to_snippet(sp) {
+            Some(s) => s,
+            None => return
+        };
+
+        let mut any = false;
+        for (i, c) in source.char_indices() {
+            if "<>+-.,[]".contains_char(c) {
+                let lo = sp.lo + codemap::BytePos(i as u32);
+                self.ignored(codemap::mk_sp(lo, lo + codemap::BytePos(1)),
+                             &*format!("`{}` inside a literal or lifetime is ignored by `brainfuck!`",
+                                       c));
+                any = true;
+            }
+        }
+        if any {
+            self.cx.span_help(sp, "use the `fidelity` option or a string literal program \
+                                   to interpret it");
+        }
+    }
+}
+
+#[derive(Clone, Copy, PartialEq)]
+enum Block {
+    Loop,
+    Proc,
+}
+
+/// Builds loops (and pbrain procedures) out of separate starts and
+/// ends, for input where nothing guarantees that they're balanced.
+pub struct BlockBuilder {
+    /// How loop starts and ends are written, for error messages.
+    open: &'static str,
+    close: &'static str,
+    /// The bodies of each enclosing block, along with the position of
+    /// its start.
+    stack: Vec<(Block, codemap::Span, Vec<Op>)>,
+    current: Vec<Op>,
+}
+
+impl BlockBuilder {
+    pub fn new(open: &'static str, close: &'static str) -> BlockBuilder {
+        BlockBuilder { open: open, close: close, stack: vec![], current: vec![] }
+    }
+
+    pub fn push(&mut self, op: Op) {
+        self.current.push(op)
+    }
+
+    pub fn open(&mut self, sp: codemap::Span) {
+        self.open_block(Block::Loop, sp)
+    }
+
+    pub fn close(&mut self, cx: &ExtCtxt, sp: codemap::Span) {
+        self.close_block(cx, Block::Loop, sp)
+    }
+
+    pub fn open_proc(&mut self, sp: codemap::Span) {
+        self.open_block(Block::Proc, sp)
+    }
+
+    pub fn close_proc(&mut self, cx: &ExtCtxt, sp: codemap::Span) {
+        self.close_block(cx, Block::Proc, sp)
+    }
+
+    fn open_block(&mut self, block: Block, sp: codemap::Span) {
+        let outer = mem::replace(&mut self.current, vec![]);
+        self.stack.push((block, sp, outer))
+    }
+
+    fn close_block(&mut self, cx: &ExtCtxt, block: Block, sp: codemap::Span) {
+        match self.stack.last() {
+            Some(&(b, _, _)) if b == block => {}
+            _ => {
+                cx.span_err(sp, &*format!("unmatched {}", self.name(block).1));
+                return
+            }
+        }
+        let (_, open_sp, outer) = self.stack.pop().unwrap();
+        let body = mem::replace(&mut self.current, outer);
+        let sp = codemap::mk_sp(open_sp.lo, sp.hi);
+        self.current.push(match block {
+            Block::Loop => Op::Loop(ir::pos(sp), body),
+            Block::Proc => Op::Proc(ir::pos(sp), body),
+        })
+    }
+
+    fn name(&self, block: Block) -> (&'static str, &'static str) {
+        match block {
+            Block::Loop => (self.open, self.close),
+            Block::Proc => ("`(`", "`)`"),
+        }
+    }
+
+    /// The whole program, reporting any blocks that weren't closed.
+    pub fn finish(self, cx: &ExtCtxt) -> Vec<Op> {
+        for &(block, open_sp, _) in self.stack.iter() {
+            cx.span_err(open_sp, &*format!("unclosed {}", self.name(block).0));
+        }
+        if self.stack.is_empty() { self.current } else { vec![] }
+    }
+}
@@ -0,0 +1,45 @@
+//! Tests for user-defined alphabets.
+
+#![feature(plugin, io)]
+
+#[plugin] extern crate brainfuck_macros;
+
+use std::old_io as io;
+use std::old_io::{BufReader, MemWriter};
+
+fn run(bf: fn(&mut Reader, &mut Writer) -> io::IoResult<Vec<u8>>) -> Vec<u8> {
+    let mut input = BufReader::new(b"");
+    let mut out = MemWriter::new();
+
+    assert!(bf(&mut input, &mut out).is_ok());
+    out.into_inner()
+}
+
+#[test]
+fn alphuck() {
+    // ++++++++[>++++++++<-]>+.
+    let bf = brainfuck!(alphabet { right = a, left = c, inc = e, dec = i,
+                                   out = j, in = o, open = p, close = s };
+                        e e e e e e e e p a e e e e e e e e c i s a e j);
+    assert_eq!(run(bf), [65]);
+}
+
+#[test]
+fn multiple_tokens() {
+    // Blub, where `!` is part of a word rather than the input
+    // separator: ++[>+<-]>.
+    let bf = brainfuck!(alphabet { right = Blub. Blub?, left = Blub? Blub.,
+                                   inc = Blub. Blub., dec = Blub! Blub!,
+                                   out = Blub! Blub., in = Blub. Blub!,
+                                   open = Blub! Blub?, close = Blub? Blub! };
+                        Blub. Blub. Blub. Blub. Blub! Blub?
+                        Blub. Blub? Blub. Blub. Blub? Blub. Blub! Blub!
+                        Blub? Blub! Blub. Blub? Blub! Blub.);
+    assert_eq!(run(bf), [2]);
+}
+
+#[test]
+fn defaults() {
+    // commands that aren't given keep their usual token
+    assert_eq!(run(brainfuck!(alphabet { inc = foo }; foo foo + [>+<-]>.)), [3]);
+}
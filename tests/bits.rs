@@ -0,0 +1,55 @@
+//! Tests for the bit-level Boolfuck and Smallfuck dialects.
+
+#![feature(plugin, io)]
+
+#[plugin] extern crate brainfuck_macros;
+
+use std::old_io as io;
+use std::old_io::{BufReader, MemWriter};
+
+fn run(bf: fn(&mut Reader, &mut Writer) -> io::IoResult<Vec<u8>>,
+       input: &[u8]) -> (Vec<u8>, Vec<u8>) {
+    let mut input = BufReader::new(input);
+    let mut out = MemWriter::new();
+
+    let tape = bf(&mut input, &mut out).unwrap();
+    (tape, out.into_inner())
+}
+
+#[test]
+fn boolfuck_output() {
+    // 65 is 01000001, written least significant bit first
+    let (_, out) = run(brainfuck!(dialect = boolfuck; +;>;;;;;<;>;), b"");
+    assert_eq!(out, [b'A']);
+    let (_, out) = run(brainfuck!(dialect = boolfuck; "+;>;;;;;<;>;"), b"");
+    assert_eq!(out, [b'A']);
+}
+
+#[test]
+fn boolfuck_partial_byte() {
+    // the last byte is padded with zeros
+    let (_, out) = run(brainfuck!(dialect = boolfuck; +;), b"");
+    assert_eq!(out, [1]);
+}
+
+#[test]
+fn boolfuck_input() {
+    // each bit is read into the same cell and written straight back
+    let (_, out) = run(brainfuck!(dialect = boolfuck; ,;,;,;,;,;,;,;,;), b"hi");
+    assert_eq!(out, [b'h']);
+    // the end of the input reads as zeros
+    let (_, out) = run(brainfuck!(dialect = boolfuck; +,;), b"");
+    assert_eq!(out, [0]);
+    let (_, out) = run(brainfuck!(dialect = boolfuck; ,;,;,;,;,;,;,;,;!h), b"");
+    assert_eq!(out, [b'h']);
+}
+
+#[test]
+fn smallfuck() {
+    // set the first three bits, find the first clear one and set it
+    let (tape, out) = run(brainfuck!(dialect = smallfuck; *>*>*<<[>]*), b"");
+    assert_eq!(tape.len(), 30_000 / 8);
+    assert_eq!(tape[0], 15);
+    assert!(tape[1..].iter().all(|&b| b == 0));
+    assert_eq!(out, []);
+}
@@ -0,0 +1,38 @@
+//! Tests for the Brainfork dialect's threads.
+
+#![feature(plugin, io)]
+
+#[plugin] extern crate brainfuck_macros;
+
+use std::old_io as io;
+use std::old_io::{BufReader, MemWriter};
+
+fn run(bf: fn(&mut Reader, &mut Writer) -> io::IoResult<Vec<u8>>) -> Vec<u8> {
+    let mut input = BufReader::new(b"");
+    let mut out = MemWriter::new();
+
+    assert!(bf(&mut input, &mut out).is_ok());
+    out.into_inner()
+}
+
+#[test]
+fn fork() {
+    // the parent's cell is zeroed, so only the child prints.
+    assert_eq!(run(brainfuck!(dialect = brainfork; +++Y[.-])), [1]);
+}
+
+#[test]
+fn round_robin() {
+    // the child is created and takes its first step in the same
+    // round as the fork, so it is one instruction ahead of the
+    // parent from then on.
+    assert_eq!(run(brainfuck!(dialect = brainfork; Y+++[.-])), [4, 3, 3, 2, 2, 1, 1]);
+    assert_eq!(run(brainfuck!(dialect = brainfork; "Y+++[.-]")), [4, 3, 3, 2, 2, 1, 1]);
+}
+
+#[test]
+fn shared_tape() {
+    // the parent spins on the cell to the right of the fork, until
+    // the child clears it.
+    assert_eq!(run(brainfuck!(dialect = brainfork; +>+<Y>[]<-.)), [0, 255]);
+}
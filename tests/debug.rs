@@ -0,0 +1,34 @@
+//! Tests for the `#` debug dump.
+
+#![feature(plugin, io)]
+
+#[plugin] extern crate brainfuck_macros;
+
+use std::old_io as io;
+use std::old_io::{BufReader, MemWriter};
+
+fn run(bf: fn(&mut Reader, &mut Writer) -> io::IoResult<Vec<u8>>) -> String {
+    let mut input = BufReader::new(b"");
+    let mut out = MemWriter::new();
+
+    assert!(bf(&mut input, &mut out).is_ok());
+    String::from_utf8(out.into_inner()).ok().expect("non-UTF8 bf output")
+}
+
+#[test]
+fn dump() {
+    let out = run(brainfuck!(debug = output; +++>++#));
+    assert!(out.contains("debug.rs:"), "{}", out);
+    assert!(out.ends_with(": pointer 1, cells 0..9: 3 [2] 0 0 0 0 0 0 0 0\n"), "{}", out);
+}
+
+#[test]
+fn dump_str() {
+    let out = run(brainfuck!(debug = output; "++++++++++>+#"));
+    assert!(out.ends_with(": pointer 1, cells 0..9: 10 [1] 0 0 0 0 0 0 0 0\n"), "{}", out);
+}
+
+#[test]
+fn off() {
+    assert_eq!(run(brainfuck!(+++>++#)), "");
+}
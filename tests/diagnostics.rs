@@ -0,0 +1,152 @@
+//! Tests for the warnings and errors `brainfuck!` reports, by
+//! expanding it directly and collecting what it says.
+
+#![feature(rustc_private)]
+
+extern crate syntax;
+extern crate brainfuck_macros;
+
+use std::sync::{Arc, Mutex};
+
+use syntax::codemap::{self, CodeMap, Span};
+use syntax::diagnostic::{self, Emitter, Level, RenderSpan};
+use syntax::ext::base::ExtCtxt;
+use syntax::ext::expand::ExpansionConfig;
+use syntax::parse;
+
+/// A diagnostic's level and message, and the byte offsets of its span
+/// in the source, if it has one.
+type Diagnostic = (Level, String, Option<(usize, usize)>);
+
+struct Collector(Arc<Mutex<Vec<Diagnostic>>>);
+
+impl Emitter for Collector {
+    fn emit(&mut self, cmsp: Option<(&CodeMap, Span)>, msg: &str, _: Option<&str>, lvl: Level) {
+        // the source is the only file in the codemap, so its offsets
+        // start at zero
+        let sp = cmsp.map(|(_, sp)| (sp.lo.0 as usize, sp.hi.0 as usize));
+        self.0.lock().unwrap().push((lvl, msg.to_string(), sp))
+    }
+
+    fn custom_emit(&mut self, _: &CodeMap, _: RenderSpan, msg: &str, lvl: Level) {
+        self.0.lock().unwrap().push((lvl, msg.to_string(), None))
+    }
+}
+
+/// Expand `brainfuck!(src)`, returning everything it reports.
+fn diagnostics(src: &str) -> Vec<Diagnostic> {
+    let found = Arc::new(Mutex::new(vec![]));
+    let handler = diagnostic::mk_handler(true, Box::new(Collector(found.clone())));
+    let sess = parse::new_parse_sess_special_handler(
+        diagnostic::mk_span_handler(handler, CodeMap::new()));
+    let mut cx = ExtCtxt::new(&sess, vec![], ExpansionConfig::default("test".to_string()));
+
+    let tts = parse::parse_tts_from_source_str("<test>".to_string(), src.to_string(),
+                                               vec![], &sess);
+    brainfuck_macros::expand_brainfuck(&mut cx, codemap::DUMMY_SP, &*tts);
+    let found = found.lock().unwrap().clone();
+    found
+}
+
+/// The messages at `level` from expanding `brainfuck!(src)`, along
+/// with the offset and the source of their spans.
+fn reported(src: &str, level: Level) -> Vec<(String, usize, &str)> {
+    diagnostics(src).into_iter().filter(|d| d.0 == level).map(|(_, msg, sp)| {
+        let (lo, hi) = sp.expect("a diagnostic without a span");
+        (msg, lo, &src[lo..hi])
+    }).collect()
+}
+
+#[test]
+fn quiet_by_default() {
+    assert_eq!(diagnostics("+ \"a+b\" '.' 'a (+) {-} -"), []);
+}
+
+#[test]
+fn hidden_in_literals() {
+    let src = "warnings; + \"a+b\" '.' -";
+    let warnings = reported(src, Level::Warning);
+    assert_eq!(warnings.len(), 2);
+    assert_eq!(warnings[0].0, "`+` inside a literal or lifetime is ignored by `brainfuck!`");
+    assert_eq!((warnings[0].1, warnings[0].2), (src.find("a+b").unwrap() + 1, "+"));
+    assert_eq!(warnings[1].0, "`.` inside a literal or lifetime is ignored by `brainfuck!`");
+    assert_eq!((warnings[1].1, warnings[1].2), (src.find("'.'").unwrap() + 1, "."));
+
+    // each literal gets a suggestion
+    let help = reported(src, Level::Help);
+    assert_eq!(help.len(), 2);
+    assert_eq!(help[0].2, "\"a+b\"");
+    assert_eq!(help[1].2, "'.'");
+    assert_eq!(reported(src, Level::Error), []);
+
+    // nothing to report in literals without commands
+    assert_eq!(diagnostics("warnings; + \"ab\" 'x' 15 -"), []);
+}
+
+#[test]
+fn lifetimes() {
+    // lifetimes are checked too, but are identifiers, which can't
+    // contain any commands
+    assert_eq!(diagnostics("warnings; + 'a 'b_c -"), []);
+}
+
+#[test]
+fn dropped_delimiters() {
+    let src = "warnings; + (+) {-[-]}";
+    let warnings = reported(src, Level::Warning);
+    assert_eq!(warnings.len(), 2);
+    assert_eq!(warnings[0].0, "`(`...`)` delimiters ignored by `brainfuck!`");
+    assert_eq!((warnings[0].1, warnings[0].2), (src.find('(').unwrap(), "("));
+    assert_eq!(warnings[1].0, "`{`...`}` delimiters ignored by `brainfuck!`");
+    assert_eq!((warnings[1].1, warnings[1].2), (src.find('{').unwrap(), "{"));
+}
+
+#[test]
+fn strict() {
+    let src = "strict; + \"a+b\" (-)";
+    assert_eq!(reported(src, Level::Warning), []);
+    let errors = reported(src, Level::Error);
+    assert_eq!(errors.len(), 2);
+    assert_eq!((errors[0].1, errors[0].2), (src.find("a+b").unwrap() + 1, "+"));
+    assert_eq!((errors[1].1, errors[1].2), (src.find('(').unwrap(), "("));
+}
+
+static PROSE: &'static str = "+ the end. a well-known fact, really";
+
+#[test]
+fn prose() {
+    // warned about by default, pointing at each command that's glued
+    // to a word
+    let warnings = reported(PROSE, Level::Warning);
+    let msg = "brainfuck command attached to a word looks like prose, but will be run";
+    assert_eq!(warnings, [(msg.to_string(), PROSE.find('.').unwrap(), "."),
+                          (msg.to_string(), PROSE.find('-').unwrap(), "-"),
+                          (msg.to_string(), PROSE.find(',').unwrap(), ",")]);
+    let help = reported(PROSE, Level::Help);
+    assert_eq!(help.len(), 3);
+    assert_eq!(help[0].0, "move the text into a Rust comment (`//` or `/* ... */`)");
+
+    // commands on their own are fine
+    assert_eq!(diagnostics("+ the end . a well - known fact , really"), []);
+}
+
+#[test]
+fn prose_levels() {
+    let deny = format!("prose = deny; {}", PROSE);
+    assert_eq!(reported(&*deny, Level::Warning), []);
+    let errors = reported(&*deny, Level::Error);
+    assert_eq!(errors.len(), 3);
+    assert_eq!((errors[0].1, errors[0].2), (deny.find('.').unwrap(), "."));
+
+    assert_eq!(diagnostics(&*format!("prose = allow; {}", PROSE)), []);
+}
+
+#[test]
+fn bf_bf_interpreter() {
+    // the program in `bf_bf_interpreter`, with its URL in a comment
+    // and its sentences without full stops, has nothing to warn about
+    let src = include_str!("../bf_bf_interpreter/src/lib.rs");
+    let start = src.find("brainfuck! {").unwrap() + "brainfuck! {".len();
+    let end = start + src[start..].find("\n    }\n").unwrap();
+    assert_eq!(diagnostics(&src[start..end]), []);
+}
@@ -0,0 +1,33 @@
+//! Tests for the Extended Brainfuck Type I dialect.
+
+#![feature(plugin, io)]
+
+#[plugin] extern crate brainfuck_macros;
+
+use std::old_io as io;
+use std::old_io::{BufReader, MemWriter};
+
+fn run(bf: fn(&mut Reader, &mut Writer) -> io::IoResult<Vec<u8>>) -> Vec<u8> {
+    let mut input = BufReader::new(b"");
+    let mut out = MemWriter::new();
+
+    assert!(bf(&mut input, &mut out).is_ok());
+    out.into_inner()
+}
+
+#[test]
+fn commands() {
+    // 8 shifted left 3 times, plus 1, is 65; shifted right 3 times is
+    // 8, which is copied to the next cell via the register, and then
+    // negated. Nothing after the `@` runs.
+    assert_eq!(run(brainfuck!(dialect = ebf1; ++++++++{{{+.}}}.$>!.~.@.)),
+               [65, 8, 8, 247]);
+    assert_eq!(run(brainfuck!(dialect = ebf1; "++++++++{{{+.}}}.$>!.~.@.")),
+               [65, 8, 8, 247]);
+}
+
+#[test]
+fn ne() {
+    // `!=` is a load
+    assert_eq!(run(brainfuck!(dialect = ebf1; +++$>!=.)), [3]);
+}
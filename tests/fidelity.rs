@@ -0,0 +1,130 @@
+//! Checks that `fidelity` mode interprets exactly the characters
+//! written in the source of every token.
+
+#![feature(plugin, io)]
+
+#[plugin] extern crate brainfuck_macros;
+
+use std::old_io as io;
+use std::old_io::{BufReader, MemWriter};
+
+type Bf = fn(&mut Reader, &mut Writer) -> io::IoResult<Vec<u8>>;
+
+static INPUT: &'static [u8] = b"ab";
+
+/// A direct interpreter for bracket-free brainfuck, returning the
+/// final tape and the output.
+fn reference(prog: &str) -> (Vec<u8>, Vec<u8>) {
+    let mut tape = vec![0u8; 30_000];
+    let mut i = 0;
+    let mut input = INPUT.iter();
+    let mut out = vec![];
+    for c in prog.chars() {
+        match c {
+            '<' => if i > 0 { i -= 1 },
+            '>' => if i < tape.len() - 1 { i += 1 },
+            '+' => tape[i] += 1,
+            '-' => tape[i] -= 1,
+            '.' => out.push(tape[i]),
+            ',' => tape[i] = input.next().map_or(-1, |&b| b),
+            _ => {}
+        }
+    }
+    (tape, out)
+}
+
+fn check(bf: Bf, source: &str) {
+    let mut input = BufReader::new(INPUT);
+    let mut out = MemWriter::new();
+    let tape = bf(&mut input, &mut out).ok().expect("bf program failed");
+
+    let (expected_tape, expected_out) = reference(source);
+    assert!(tape == expected_tape, "tape mismatch for `{}`", source);
+    assert!(out.into_inner() == expected_out, "output mismatch for `{}`", source);
+}
+
+// Every token is run with the pointer away from the edge of the tape
+// and a non-zero current cell, with a `+` after it to mark where the
+// pointer ends up.
+macro_rules! table {
+    ($($name: ident: ($($tok: tt)*),)*) => {
+        $(
+            #[test]
+            fn $name() {
+                check(brainfuck!(fidelity; >>> +++ $($tok)* +),
+                      concat!(">>>+++", stringify!($($tok)*), "+"))
+            }
+        )*
+    }
+}
+
+// `!` isn't here, since it separates the program from its input.
+table! {
+    eq: (=),
+    lt: (<),
+    le: (<=),
+    eq_eq: (==),
+    ne: (!=),
+    ge: (>=),
+    gt: (>),
+    and_and: (&&),
+    or_or: (||),
+    tilde: (~),
+    plus: (+),
+    minus: (-),
+    star: (*),
+    slash: (/),
+    percent: (%),
+    caret: (^),
+    and: (&),
+    or: (|),
+    shl: (<<),
+    shr: (>>),
+    plus_eq: (+=),
+    minus_eq: (-=),
+    star_eq: (*=),
+    slash_eq: (/=),
+    percent_eq: (%=),
+    caret_eq: (^=),
+    and_eq: (&=),
+    or_eq: (|=),
+    shl_eq: (<<=),
+    shr_eq: (>>=),
+    at: (@),
+    dot: (.),
+    dot_dot: (..),
+    dot_dot_dot: (...),
+    comma: (,),
+    semi: (;),
+    colon: (:),
+    mod_sep: (::),
+    r_arrow: (->),
+    l_arrow: (<-),
+    fat_arrow: (=>),
+    pound: (#),
+    dollar: ($),
+    question: (?),
+    underscore: (_),
+
+    str_lit: ("+.-,"),
+    raw_str_lit: (r"<+>"),
+    char_lit: ('.'),
+    byte_lit: (b','),
+    byte_str_lit: (b"->"),
+    ident: (foo_bar),
+    lifetime: ('a),
+}
+
+#[test]
+fn loop_across_tokens() {
+    // a loop can start in one token and end in another
+    let bf = brainfuck!(fidelity; "+++[>++" "<-]>.");
+    let mut out = MemWriter::new();
+    bf(&mut BufReader::new(b""), &mut out).unwrap();
+    assert_eq!(out.into_inner(), [6]);
+
+    let bf = brainfuck!(fidelity; + '[' > b"+<-" ']' > .);
+    let mut out = MemWriter::new();
+    bf(&mut BufReader::new(b""), &mut out).unwrap();
+    assert_eq!(out.into_inner(), [1]);
+}
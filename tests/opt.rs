@@ -0,0 +1,71 @@
+//! Tests that every optimisation level gives the same results.
+
+#![feature(plugin, io)]
+
+#[plugin] extern crate brainfuck_macros;
+
+use std::old_io as io;
+use std::old_io::{BufReader, MemWriter};
+
+type Program = fn(&mut Reader, &mut Writer) -> io::IoResult<Vec<u8>>;
+
+/// The program compiled at each optimisation level, and with some
+/// passes turned on or off by name.
+macro_rules! levels {
+    ($($code: tt)*) => {
+        [brainfuck!(opt = 0; $($code)*),
+         brainfuck!(opt = 1; $($code)*),
+         brainfuck!(opt = 2; $($code)*),
+         brainfuck!(opt = 3; $($code)*),
+         brainfuck!(opt = 3, passes { dead_loops = off, combine = off }; $($code)*),
+         brainfuck!(opt = 3, passes { clear = off, scan = off }; $($code)*),
+         brainfuck!(opt = 0, passes { mul = on }; $($code)*)]
+    }
+}
+
+/// Run each program, checking that they all agree on the tape and
+/// output, and that the output is `expected`.
+fn run(programs: &[Program], expected: &[u8]) {
+    let mut results = programs.iter().map(|bf| {
+        let mut input = BufReader::new(b"");
+        let mut out = MemWriter::new();
+        let tape = bf(&mut input, &mut out).unwrap();
+        (tape, out.into_inner())
+    });
+
+    let (first_tape, first_out) = results.next().unwrap();
+    assert_eq!(&*first_out, expected);
+    for (tape, out) in results {
+        assert!(tape == first_tape);
+        assert_eq!(out, first_out);
+    }
+}
+
+#[test]
+fn hello_world() {
+    run(&levels! {
+        ++++++++[>++++[>++>+++>+++>+<<<<-]>+>+>->>+[<]<-]>>.>
+        ---.+++++++..+++.>>.<-.<.+++.------.--------.>>+.>++.
+    }, b"Hello World!\n");
+}
+
+#[test]
+fn mul() {
+    run(&levels!(+++[->++>+++<<]>.>.), &[6, 9]);
+    // the loop starts right next to the end of the tape, so it has to
+    // run one step at a time to clamp the pointer the same way
+    run(&levels!(+[>+]<[->>+<<]<.), &[1]);
+    // `<<` is clamped at the first cell, which is still zero, so the
+    // loop is skipped
+    run(&levels!(>+<<[->+<]>.), &[1]);
+}
+
+#[test]
+fn scan() {
+    run(&levels!(>+>+>+<<[>]+<[<]>.>.>.>.>.), &[1, 1, 1, 1, 0]);
+}
+
+#[test]
+fn clear() {
+    run(&levels!(+[>+]<<<[-]>[-]<<[-<]>.), &[0]);
+}
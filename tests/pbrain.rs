@@ -0,0 +1,49 @@
+//! Tests for the pbrain dialect's procedures.
+
+#![feature(plugin, io)]
+
+#[plugin] extern crate brainfuck_macros;
+
+use std::old_io as io;
+use std::old_io::{BufReader, MemWriter};
+
+fn run(bf: fn(&mut Reader, &mut Writer) -> io::IoResult<Vec<u8>>,
+       input: &str) -> io::IoResult<String> {
+    let mut input = BufReader::new(input.as_bytes());
+    let mut out = MemWriter::new();
+
+    try!(bf(&mut input, &mut out));
+    Ok(String::from_utf8(out.into_inner()).ok().expect("non-UTF8 bf output"))
+}
+
+#[test]
+fn call() {
+    // procedure 1 adds 64 to the current cell and prints it; it's
+    // called twice.
+    let bf = brainfuck!(dialect = pbrain; +(>++++++++[<++++++++>-]<.): [-]+:);
+    assert_eq!(run(bf, "").unwrap(), "AA");
+}
+
+#[test]
+fn call_str() {
+    let bf = brainfuck!(dialect = pbrain; "+(>++++++++[<++++++++>-]<.): [-]+:");
+    assert_eq!(run(bf, "").unwrap(), "AA");
+}
+
+#[test]
+fn recursive() {
+    // procedure 2 does nothing, and procedure 1 prints `.` and then
+    // calls the procedure numbered by the next byte of input.
+    let bf = brainfuck!(dialect = pbrain;
+                        ++() [-]+
+                        (>++++++[>+++++++<-]>++++.[-]<<,:)
+                        :);
+    assert_eq!(run(bf, "\x01\x01\x02").unwrap(), "...");
+    assert!(run(bf, "\x01\x00").is_err());
+}
+
+#[test]
+fn undefined() {
+    let bf = brainfuck!(dialect = pbrain; ++(+)+:);
+    assert!(run(bf, "").is_err());
+}
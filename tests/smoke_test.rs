@@ -89,3 +89,81 @@ fn cat() {
         run(bf, &*s, &*s)
     }
 }
+
+#[test]
+fn hello_world_str() {
+    run(brainfuck!("
+        ++++++++[>++++[>++>+++>+++>+<<<<-]>+>+>->>+[<]<-]>>.>
+        ---.+++++++..+++.>>.<-.<.+++.------.--------.>>+.>++.
+    "), "", "Hello World!\n");
+}
+
+#[test]
+fn hello_world_raw_str_prose() {
+    // none of this is valid Rust, and the commands inside the quotes
+    // would be silently dropped by the token-tree form.
+    run(brainfuck!(r#"
+        it's "++++++++"[>++++[>++>+++>+++>+<<<<-]>+>+>->>+[<]<-]>>.>
+        ---.+++++++..+++.>>.<-.<.+++.------.--------.>>+.>++. # \ ' "
+    "#), "", "Hello World!\n");
+}
+
+#[test]
+fn hello_world_strict() {
+    run(brainfuck!{
+        strict;
+        ++++++++[>++++[>++>+++>+++>+<<<<-]>+>+>->>+[<]<-]>>.>
+        ---.+++++++..+++.>>.<-.<.+++.------.--------.>>+.>++.
+    }, "", "Hello World!\n");
+}
+
+#[test]
+fn starts_with_option_name() {
+    // without a `;`, these are just comments
+    run(brainfuck!{
+        debug this loop
+        ++++++++[>++++[>++>+++>+++>+<<<<-]>+>+>->>+[<]<-]>>.>
+        ---.+++++++..+++.>>.<-.<.+++.------.--------.>>+.>++.
+    }, "", "Hello World!\n");
+    run(brainfuck!{ opt out of nothing but a cat ,+[-.,+] }, "meow", "meow");
+}
+
+#[test]
+fn dead_loops() {
+    // the first loop is never entered (the tape starts zeroed), nor
+    // is the one directly after the main loop.
+    run(brainfuck!{
+        [ a comment , with . commands - in it ]
+        ++++++++[>++++[>++>+++>+++>+<<<<-]>+>+>->>+[<]<-][ , . ]>>.>
+        ---.+++++++..+++.>>.<-.<.+++.------.--------.>>+.>++.
+    }, "", "Hello World!\n");
+}
+
+#[test]
+fn hello_world_ook() {
+    // ++++++++[>++++[>++>+++>+++>+<<<<-]>+>+>->>+[<]<-]>>.>
+    // ---.+++++++..+++.>>.<-.<.+++.------.--------.>>+.>++.
+    run(ook!{
+        Ook. Ook. Ook. Ook. Ook. Ook. Ook. Ook. Ook. Ook. Ook. Ook. Ook. Ook. Ook. Ook.
+        Ook! Ook? Ook. Ook? Ook. Ook. Ook. Ook. Ook. Ook. Ook. Ook. Ook! Ook? Ook. Ook?
+        Ook. Ook. Ook. Ook. Ook. Ook? Ook. Ook. Ook. Ook. Ook. Ook. Ook. Ook? Ook. Ook.
+        Ook. Ook. Ook. Ook. Ook. Ook? Ook. Ook. Ook? Ook. Ook? Ook. Ook? Ook. Ook? Ook.
+        Ook! Ook! Ook? Ook! Ook. Ook? Ook. Ook. Ook. Ook? Ook. Ook. Ook. Ook? Ook! Ook!
+        Ook. Ook? Ook. Ook? Ook. Ook. Ook! Ook? Ook? Ook. Ook? Ook! Ook? Ook. Ook! Ook!
+        Ook? Ook! Ook. Ook? Ook. Ook? Ook! Ook. Ook. Ook? Ook! Ook! Ook! Ook! Ook! Ook!
+        Ook! Ook. Ook. Ook. Ook. Ook. Ook. Ook. Ook. Ook. Ook. Ook. Ook. Ook. Ook. Ook.
+        Ook! Ook. Ook! Ook. Ook. Ook. Ook. Ook. Ook. Ook. Ook! Ook. Ook. Ook? Ook. Ook?
+        Ook! Ook. Ook? Ook. Ook! Ook! Ook! Ook. Ook? Ook. Ook! Ook. Ook. Ook. Ook. Ook.
+        Ook. Ook. Ook! Ook. Ook! Ook! Ook! Ook! Ook! Ook! Ook! Ook! Ook! Ook! Ook! Ook!
+        Ook! Ook. Ook! Ook! Ook! Ook! Ook! Ook! Ook! Ook! Ook! Ook! Ook! Ook! Ook! Ook!
+        Ook! Ook! Ook! Ook. Ook. Ook? Ook. Ook? Ook. Ook. Ook! Ook. Ook. Ook? Ook. Ook.
+        Ook. Ook. Ook! Ook.
+    }, "", "Hello World!\n");
+}
+
+#[test]
+fn embedded_input() {
+    run(brainfuck!{ ,+[-.,+] ! hello }, " world", "hello world");
+    run(brainfuck!{ ,+[-.,+] ! "it's \"quoted\"" }, "", "it's \"quoted\"");
+    run(brainfuck!(",+[-.,+]!hello"), " world", "hello world");
+}